@@ -0,0 +1,79 @@
+#version 450
+
+// The passes of the bloom in src/lib.rs, one of which is selected by
+// defining PREFILTER, DOWNSAMPLE, UPSAMPLE or COMPOSITE.
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform BloomParams {
+    float threshold;
+    float knee;
+    // divided by the number of levels
+    float intensity;
+    float radius;
+};
+layout(set = 0, binding = 1) uniform sampler s_input;
+// the input of this pass
+layout(set = 1, binding = 0) uniform texture2D t_source;
+#ifdef COMPOSITE
+// the input of the whole bloom
+layout(set = 2, binding = 0) uniform texture2D t_base;
+#endif
+
+vec3 sample_source(vec2 at) {
+    return texture(sampler2D(t_source, s_input), at).rgb;
+}
+
+// A 4x4 box filter in 4 fetches, using the linear filtering between the
+// texels
+vec3 downsample() {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(t_source, s_input), 0));
+    vec4 d = texel.xyxy * vec4(-1.0, -1.0, 1.0, 1.0);
+    return (sample_source(uv + d.xy) + sample_source(uv + d.zy) +
+            sample_source(uv + d.xw) + sample_source(uv + d.zw)) * 0.25;
+}
+
+// A 3x3 tent filter, spread by the radius
+vec3 upsample() {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(t_source, s_input), 0));
+    vec4 d = texel.xyxy * vec4(1.0, 1.0, -1.0, 0.0) * radius;
+    vec3 color = sample_source(uv - d.xy);
+    color += sample_source(uv - d.wy) * 2.0;
+    color += sample_source(uv - d.zy);
+    color += sample_source(uv + d.zw) * 2.0;
+    color += sample_source(uv) * 4.0;
+    color += sample_source(uv + d.xw) * 2.0;
+    color += sample_source(uv + d.zy);
+    color += sample_source(uv + d.wy) * 2.0;
+    color += sample_source(uv + d.xy);
+    return color / 16.0;
+}
+
+// Keeps the part of the color above the threshold, with a quadratic curve
+// around it so that the bloom doesn't pop in
+vec3 bright_pass(vec3 color) {
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft_knee = threshold * knee + 1e-5;
+    float soft = clamp(brightness - threshold + soft_knee, 0.0, 2.0 * soft_knee);
+    soft = soft * soft / (4.0 * soft_knee);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 1e-5);
+    return color * contribution;
+}
+
+void main() {
+#if defined(PREFILTER)
+    f_color = vec4(bright_pass(downsample()), 1.0);
+#elif defined(DOWNSAMPLE)
+    f_color = vec4(downsample(), 1.0);
+#elif defined(UPSAMPLE)
+    f_color = vec4(upsample(), 1.0);
+#elif defined(COMPOSITE)
+    // The source is the top of the mip chain
+    vec3 base = texture(sampler2D(t_base, s_input), uv).rgb;
+    f_color = vec4(base + upsample() * intensity, 1.0);
+#else
+#error "define one of PREFILTER, DOWNSAMPLE, UPSAMPLE or COMPOSITE"
+#endif
+}
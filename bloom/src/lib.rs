@@ -0,0 +1,404 @@
+//! A bloom post-process for sketches rendering with wgpu, shared so that
+//! any of them can add a glow to what it has rendered.
+//!
+//! The shaders are GLSL in [`SHADER_DIR`], and are compiled by the sketch
+//! through the closure given to [`Bloom::new`], e.g. with the compiler it
+//! uses for its own shaders. The closure takes the path of the shader and
+//! the macros to define, and returns SPIR-V. Watch [`SHADER_DIR`] and call
+//! [`Bloom::reload`] to edit them while the sketch is running.
+
+use nannou::prelude::*;
+
+use std::cell::Cell;
+use std::path::Path;
+
+/// The directory of `fullscreen.vert` and `bloom.frag`
+pub const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
+
+/// The look of the bloom, which can be changed at any time
+#[derive(Clone, Copy, Debug)]
+pub struct BloomSettings {
+    /// The brightness above which the pixels bloom
+    pub threshold: f32,
+    /// How gradually the pixels start to bloom below the threshold, as a
+    /// fraction of it. 0.0 is a hard cut.
+    pub knee: f32,
+    /// How much of the bloom is added on top of the input
+    pub intensity: f32,
+    /// The spread of the upsampling filter in texels. The larger, the
+    /// blurrier the glow.
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            knee: 0.5,
+            intensity: 1.0,
+            radius: 1.0,
+        }
+    }
+}
+
+// The uniform block `BloomParams` in bloom.frag
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+struct BloomUniforms {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    radius: f32,
+}
+
+unsafe impl bytemuck::Pod for BloomUniforms {}
+unsafe impl bytemuck::Zeroable for BloomUniforms {}
+
+const UNIFORMS_SIZE: wgpu::BufferAddress =
+    std::mem::size_of::<BloomUniforms>() as wgpu::BufferAddress;
+
+// The upsampled levels are added to the downsampled ones
+const ADDITIVE: wgpu::BlendDescriptor = wgpu::BlendDescriptor {
+    src_factor: wgpu::BlendFactor::One,
+    dst_factor: wgpu::BlendFactor::One,
+    operation: wgpu::BlendOperation::Add,
+};
+
+// The bind group of the uniforms and the sampler, shared by all the passes
+const PARAMS_SET: u32 = 0;
+// The bind group of the texture a pass reads
+const SOURCE_SET: u32 = 1;
+// The bind group of the input of the whole bloom, only read by the
+// composite pass
+const BASE_SET: u32 = 2;
+
+struct BloomPipelines {
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+}
+
+struct Mip {
+    // kept alive for the view
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    // the view at `SOURCE_SET`, for the pass that reads this level
+    bind_group: wgpu::BindGroup,
+}
+
+/// A bloom post-process. The bright parts of the input are extracted at
+/// half the size, downsampled into a chain of smaller and smaller textures,
+/// upsampled back with each level added to the one above it, and added on
+/// top of the input.
+///
+/// It takes any texture of `Frame::TEXTURE_FORMAT` as the input and writes
+/// to any view of the same format, so it works on whatever a sketch has
+/// rendered, e.g. an intermediate texture, with the frame as the output.
+pub struct Bloom {
+    pub settings: BloomSettings,
+    levels: usize,
+    output_samples: u32,
+    size: [u32; 2],
+    params_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    params_bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    // what's in `uniform_buffer`, so that it's uploaded only when the
+    // settings change
+    uploaded: Cell<Option<BloomUniforms>>,
+    pipelines: BloomPipelines,
+    // from half the size of the input down
+    mips: Vec<Mip>,
+}
+
+impl Bloom {
+    /// `levels` is the length of the mip chain, which decides how far the
+    /// glow can reach. `output_samples` is the MSAA sample count of the
+    /// output.
+    pub fn new(
+        device: &wgpu::Device,
+        size: [u32; 2],
+        levels: usize,
+        output_samples: u32,
+        compile: impl FnMut(&Path, &[&str]) -> Result<Vec<u8>, String>,
+    ) -> Result<Self, String> {
+        let params_layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStage::FRAGMENT, false)
+            .sampler(wgpu::ShaderStage::FRAGMENT)
+            .build(device);
+        // The textures are bound one per group, so that the groups of the
+        // mip chain are created once per resize and only the one of the
+        // input is created for each call
+        let texture_layout = wgpu::BindGroupLayoutBuilder::new()
+            .sampled_texture(
+                wgpu::ShaderStage::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                wgpu::TextureComponentType::Float,
+            )
+            .build(device);
+
+        let sampler = wgpu::SamplerBuilder::new()
+            .mag_filter(wgpu::FilterMode::Linear)
+            .min_filter(wgpu::FilterMode::Linear)
+            .build(device);
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bloom"),
+            size: UNIFORMS_SIZE,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let params_bind_group = wgpu::BindGroupBuilder::new()
+            .buffer::<BloomUniforms>(&uniform_buffer, 0..1)
+            .sampler(&sampler)
+            .build(device, &params_layout);
+
+        let pipelines = build_pipelines(
+            device,
+            &params_layout,
+            &texture_layout,
+            output_samples,
+            compile,
+        )?;
+
+        let mut bloom = Self {
+            settings: BloomSettings::default(),
+            levels: levels.max(1),
+            output_samples,
+            size: [0, 0],
+            params_layout,
+            texture_layout,
+            params_bind_group,
+            uniform_buffer,
+            uploaded: Cell::new(None),
+            pipelines,
+            mips: vec![],
+        };
+        bloom.resize(device, size);
+        Ok(bloom)
+    }
+
+    pub fn settings(mut self, settings: BloomSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Rebuilds the pipelines, or keeps the current ones if the shaders don't
+    /// compile.
+    pub fn reload(
+        &mut self,
+        device: &wgpu::Device,
+        compile: impl FnMut(&Path, &[&str]) -> Result<Vec<u8>, String>,
+    ) -> Result<(), String> {
+        self.pipelines = build_pipelines(
+            device,
+            &self.params_layout,
+            &self.texture_layout,
+            self.output_samples,
+            compile,
+        )?;
+        Ok(())
+    }
+
+    /// Recreates the mip chain and its bind groups if the size of the input
+    /// has changed.
+    pub fn resize(&mut self, device: &wgpu::Device, size: [u32; 2]) {
+        if size == self.size {
+            return;
+        }
+        self.size = size;
+
+        self.mips.clear();
+        let [mut w, mut h] = size;
+        for _ in 0..self.levels {
+            w = (w / 2).max(1);
+            h = (h / 2).max(1);
+            let texture = wgpu::TextureBuilder::new()
+                .size([w, h])
+                .format(Frame::TEXTURE_FORMAT)
+                .usage(wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED)
+                .build(device);
+            let view = texture.create_default_view();
+            let bind_group = self.texture_bind_group(device, &view);
+            self.mips.push(Mip {
+                _texture: texture,
+                view,
+                bind_group,
+            });
+            // no point in going further
+            if w == 1 && h == 1 {
+                break;
+            }
+        }
+    }
+
+    /// Records the passes that write `input` with the bloom added to
+    /// `output`. The input must be of the size given to `resize`.
+    ///
+    /// The settings are uploaded only if they have changed since the last
+    /// call.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+    ) {
+        // Every level adds up in the end, so the intensity is divided among
+        // them to keep the brightness independent of the length of the chain
+        let settings = &self.settings;
+        let uniforms = BloomUniforms {
+            threshold: settings.threshold,
+            knee: settings.knee,
+            intensity: settings.intensity / self.mips.len() as f32,
+            radius: settings.radius,
+        };
+        self.upload(device, encoder, &uniforms);
+
+        // The input can be any texture, so its bind group is the only one
+        // created for each call
+        let input_group = self.texture_bind_group(device, input);
+        let last = self.mips.len() - 1;
+
+        let pipelines = &self.pipelines;
+        let mut pass = |target: &wgpu::TextureView,
+                        clear: bool,
+                        pipeline: &wgpu::RenderPipeline,
+                        source: &wgpu::BindGroup,
+                        base: Option<&wgpu::BindGroup>| {
+            let mut render_pass = begin(encoder, target, clear);
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(PARAMS_SET, &self.params_bind_group, &[]);
+            render_pass.set_bind_group(SOURCE_SET, source, &[]);
+            if let Some(base) = base {
+                render_pass.set_bind_group(BASE_SET, base, &[]);
+            }
+            render_pass.draw(0..3, 0..1);
+        };
+
+        pass(
+            &self.mips[0].view,
+            true,
+            &pipelines.prefilter,
+            &input_group,
+            None,
+        );
+        for i in 1..=last {
+            pass(
+                &self.mips[i].view,
+                true,
+                &pipelines.downsample,
+                &self.mips[i - 1].bind_group,
+                None,
+            );
+        }
+        // Blended on top of the downsampled level, which isn't cleared
+        for i in (0..last).rev() {
+            pass(
+                &self.mips[i].view,
+                false,
+                &pipelines.upsample,
+                &self.mips[i + 1].bind_group,
+                None,
+            );
+        }
+        pass(
+            output,
+            true,
+            &pipelines.composite,
+            &self.mips[0].bind_group,
+            Some(&input_group),
+        );
+    }
+
+    fn texture_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        wgpu::BindGroupBuilder::new()
+            .texture_view(view)
+            .build(device, &self.texture_layout)
+    }
+
+    // Records the copy of the uniforms if they differ from the last ones.
+    // The staging buffer is mapped at creation, so writing it doesn't wait
+    // for the GPU, and wgpu frees it once the copy is done. It's created
+    // only when the settings change, which is rare enough not to keep one.
+    fn upload(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uniforms: &BloomUniforms,
+    ) {
+        if self.uploaded.get() == Some(*uniforms) {
+            return;
+        }
+        let staging_buffer = device
+            .create_buffer_with_data(bytemuck::bytes_of(uniforms), wgpu::BufferUsage::COPY_SRC);
+        encoder.copy_buffer_to_buffer(&staging_buffer, 0, &self.uniform_buffer, 0, UNIFORMS_SIZE);
+        self.uploaded.set(Some(*uniforms));
+    }
+}
+
+// Begins a pass drawing a fullscreen triangle
+fn begin<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    target: &'a wgpu::TextureView,
+    clear: bool,
+) -> wgpu::RenderPass<'a> {
+    wgpu::RenderPassBuilder::new()
+        .color_attachment(target, |color| {
+            if clear {
+                color.clear_color(wgpu::Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                })
+            } else {
+                // the contents are loaded by default
+                color
+            }
+        })
+        .begin(encoder)
+}
+
+fn build_pipelines(
+    device: &wgpu::Device,
+    params_layout: &wgpu::BindGroupLayout,
+    texture_layout: &wgpu::BindGroupLayout,
+    output_samples: u32,
+    mut compile: impl FnMut(&Path, &[&str]) -> Result<Vec<u8>, String>,
+) -> Result<BloomPipelines, String> {
+    let mut load = |name: &str, defines: &[&str]| -> Result<wgpu::ShaderModule, String> {
+        let spirv = compile(&Path::new(SHADER_DIR).join(name), defines)?;
+        Ok(wgpu::shader_from_spirv_bytes(device, &spirv))
+    };
+    let vs_mod = load("fullscreen.vert", &[])?;
+    let fs_mod_prefilter = load("bloom.frag", &["PREFILTER"])?;
+    let fs_mod_downsample = load("bloom.frag", &["DOWNSAMPLE"])?;
+    let fs_mod_upsample = load("bloom.frag", &["UPSAMPLE"])?;
+    let fs_mod_composite = load("bloom.frag", &["COMPOSITE"])?;
+
+    let pipeline_layout = wgpu::create_pipeline_layout(device, &[params_layout, texture_layout]);
+    let composite_layout =
+        wgpu::create_pipeline_layout(device, &[params_layout, texture_layout, texture_layout]);
+    let pipeline = |layout: &wgpu::PipelineLayout, fs_mod: &wgpu::ShaderModule| {
+        wgpu::RenderPipelineBuilder::from_layout(layout, &vs_mod)
+            .fragment_shader(fs_mod)
+            .color_format(Frame::TEXTURE_FORMAT)
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+    };
+    Ok(BloomPipelines {
+        prefilter: pipeline(&pipeline_layout, &fs_mod_prefilter).build(device),
+        downsample: pipeline(&pipeline_layout, &fs_mod_downsample).build(device),
+        upsample: pipeline(&pipeline_layout, &fs_mod_upsample)
+            .color_blend(ADDITIVE)
+            .alpha_blend(ADDITIVE)
+            .build(device),
+        composite: pipeline(&composite_layout, &fs_mod_composite)
+            .sample_count(output_samples)
+            .build(device),
+    })
+}
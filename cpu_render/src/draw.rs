@@ -0,0 +1,199 @@
+use nannou::geom::{pt2, Point2};
+
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+
+use crate::primitive::{
+    Background, Ellipse, Line, Mesh, PathInit, Polygon, Polyline, Primitive, Rect,
+};
+
+/// A 2D affine transform, which maps `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// Counterclockwise rotation (as the y axis points up)
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Applies `self` after `other`.
+    pub fn then(&self, other: &Transform) -> Self {
+        Self::new(
+            self.a * other.a + self.c * other.b,
+            self.b * other.a + self.d * other.b,
+            self.a * other.c + self.c * other.d,
+            self.b * other.c + self.d * other.d,
+            self.a * other.e + self.c * other.f + self.e,
+            self.b * other.e + self.d * other.f + self.f,
+        )
+    }
+
+    pub fn apply(&self, p: Point2) -> Point2 {
+        pt2(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// The average scale, which is used to decide how finely curves are
+    /// flattened.
+    pub fn scale_factor(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+pub(crate) struct Command {
+    pub transform: Transform,
+    pub primitive: Primitive,
+}
+
+/// Records primitives to render, like nannou's `Draw`. Clones share the
+/// same list of primitives; the methods that change the transform return
+/// such a clone.
+#[derive(Clone, Default)]
+pub struct Draw {
+    commands: Rc<RefCell<Vec<Command>>>,
+    transform: Transform,
+}
+
+impl Draw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all the primitives drawn so far.
+    pub fn reset(&self) {
+        self.commands.borrow_mut().clear();
+    }
+
+    /// Applies `transform` to everything drawn with the returned `Draw`.
+    pub fn transform(&self, transform: Transform) -> Self {
+        Self {
+            commands: self.commands.clone(),
+            transform: self.transform.then(&transform),
+        }
+    }
+
+    pub fn x_y(&self, x: f32, y: f32) -> Self {
+        self.transform(Transform::translation(x, y))
+    }
+
+    pub fn xy(&self, p: Point2) -> Self {
+        self.x_y(p.x, p.y)
+    }
+
+    pub fn rotate(&self, radians: f32) -> Self {
+        self.transform(Transform::rotation(radians))
+    }
+
+    pub fn scale(&self, s: f32) -> Self {
+        self.transform(Transform::scale(s, s))
+    }
+
+    pub fn background(&self) -> Drawing<Background> {
+        Drawing::new(self, Background::default())
+    }
+
+    pub fn rect(&self) -> Drawing<Rect> {
+        Drawing::new(self, Rect::default())
+    }
+
+    pub fn ellipse(&self) -> Drawing<Ellipse> {
+        Drawing::new(self, Ellipse::default())
+    }
+
+    pub fn line(&self) -> Drawing<Line> {
+        Drawing::new(self, Line::default())
+    }
+
+    pub fn polyline(&self) -> Drawing<Polyline> {
+        Drawing::new(self, Polyline::default())
+    }
+
+    pub fn polygon(&self) -> Drawing<Polygon> {
+        Drawing::new(self, Polygon::default())
+    }
+
+    /// Call `.fill()` or `.stroke()` on this to draw a path.
+    pub fn path(&self) -> PathInit {
+        PathInit { draw: self.clone() }
+    }
+
+    pub fn mesh(&self) -> Drawing<Mesh> {
+        Drawing::new(self, Mesh::default())
+    }
+
+    pub(crate) fn commands(&self) -> Ref<'_, Vec<Command>> {
+        self.commands.borrow()
+    }
+
+    fn push(&self, primitive: Primitive) {
+        self.commands.borrow_mut().push(Command {
+            transform: self.transform,
+            primitive,
+        });
+    }
+}
+
+/// A primitive being built. Like nannou's `Drawing`, it's added to the
+/// `Draw` when dropped, i.e. at the end of the statement.
+pub struct Drawing<T: Into<Primitive>> {
+    draw: Draw,
+    primitive: Option<T>,
+}
+
+impl<T: Into<Primitive>> Drawing<T> {
+    pub(crate) fn new(draw: &Draw, primitive: T) -> Self {
+        Self {
+            draw: draw.clone(),
+            primitive: Some(primitive),
+        }
+    }
+
+    pub(crate) fn map<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Some(primitive) = self.primitive.as_mut() {
+            f(primitive);
+        }
+        self
+    }
+}
+
+impl<T: Into<Primitive>> Drop for Drawing<T> {
+    fn drop(&mut self) {
+        if let Some(primitive) = self.primitive.take() {
+            self.draw.push(primitive.into());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use nannou::image::{Rgba, RgbaImage};
+
+use std::path::{Path, PathBuf};
+
+use crate::draw::Draw;
+use crate::input::Input;
+
+// The maximum of `color_delta()`
+const MAX_DELTA: f32 = 35215.0;
+
+/// Compares rendered images against reference images checked in to `dir`,
+/// e.g. `tests/golden/example1.png`.
+///
+/// Set the `UPDATE_GOLDEN` environment variable to (re)write the reference
+/// images instead:
+///
+/// ```text
+/// UPDATE_GOLDEN=1 cargo test
+/// ```
+pub struct Golden {
+    dir: PathBuf,
+    threshold: f32,
+    max_diff_ratio: f32,
+}
+
+impl Golden {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            threshold: 0.1,
+            max_diff_ratio: 0.001,
+        }
+    }
+
+    /// How different two pixels can be to be considered the same, from 0.0
+    /// to 1.0. The difference is measured in the YIQ colour space, which is
+    /// closer to the perception than RGB.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The ratio of different pixels allowed, which absorbs the small
+    /// differences of anti-aliasing
+    pub fn max_diff_ratio(mut self, max_diff_ratio: f32) -> Self {
+        self.max_diff_ratio = max_diff_ratio;
+        self
+    }
+
+    /// Compares `image` against the reference image `<name>.png`. On
+    /// failure, `<name>.actual.png` and `<name>.diff.png` are written next to
+    /// the reference, where the different pixels are red on the diff image.
+    pub fn check(&self, name: &str, image: &RgbaImage) -> Result<(), String> {
+        let path = self.dir.join(name).with_extension("png");
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("failed to create {}: {}", self.dir.display(), e))?;
+            image
+                .save(&path)
+                .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Updated {}", path.display());
+            return Ok(());
+        }
+
+        let expected = match nannou::image::open(&path) {
+            Ok(expected) => expected.to_rgba8(),
+            Err(e) => {
+                return Err(format!(
+                    "failed to read {}: {}\nRun with UPDATE_GOLDEN=1 to create it",
+                    path.display(),
+                    e
+                ))
+            }
+        };
+
+        if expected.dimensions() != image.dimensions() {
+            self.save_actual(name, image);
+            return Err(format!(
+                "{}: the size is {:?}, but expected {:?}",
+                name,
+                image.dimensions(),
+                expected.dimensions()
+            ));
+        }
+
+        let max_delta = self.threshold * self.threshold * MAX_DELTA;
+        let mut n_diff = 0;
+        let diff = RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+            let (a, b) = (image.get_pixel(x, y), expected.get_pixel(x, y));
+            if color_delta(a, b) > max_delta {
+                n_diff += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                // a faded grayscale of the expected image for the context
+                let gray = (255.0 - (255.0 - luma(b)) * 0.1) as u8;
+                Rgba([gray, gray, gray, 255])
+            }
+        });
+
+        let ratio = n_diff as f32 / (image.width() * image.height()).max(1) as f32;
+        if ratio <= self.max_diff_ratio {
+            return Ok(());
+        }
+
+        self.save_actual(name, image);
+        let diff_path = self.dir.join(format!("{}.diff.png", name));
+        if let Err(e) = diff.save(&diff_path) {
+            eprintln!("Failed to write {}: {}", diff_path.display(), e);
+        }
+
+        Err(format!(
+            "{}: {} pixels ({:.2}%) differ from {} (see {})",
+            name,
+            n_diff,
+            ratio * 100.0,
+            path.display(),
+            diff_path.display()
+        ))
+    }
+
+    fn save_actual(&self, name: &str, image: &RgbaImage) {
+        let path = self.dir.join(format!("{}.actual.png", name));
+        if let Err(e) = image.save(&path) {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Draws a scene for `input`, renders it and compares it against
+/// `tests/golden/<name>.png` of the crate being tested, which makes the
+/// whole body of a sketch's golden test:
+///
+/// ```no_run
+/// # fn scene(draw: &cpu_render::Draw, input: &cpu_render::Input) {}
+/// let input = cpu_render::Input::new(1024, 768);
+/// cpu_render::assert_scene("example1", &input, scene);
+/// ```
+///
+/// Panics with the difference if the images differ.
+pub fn assert_scene<F>(name: &str, input: &Input, scene: F)
+where
+    F: FnOnce(&Draw, &Input),
+{
+    // set by cargo when running the tests, unlike `env!()` which would be
+    // the directory of this crate
+    let dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .expect("CARGO_MANIFEST_DIR is not set; run the tests with cargo")
+        .join("tests/golden");
+
+    let draw = Draw::new();
+    scene(&draw, input);
+    let image = input.renderer().render(&draw);
+
+    if let Err(e) = Golden::new(dir).check(name, &image) {
+        panic!("{}", e);
+    }
+}
+
+// The squared distance in YIQ, after blending the colours with white. This
+// is the metric used by pixelmatch.
+fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let (ya, ia, qa) = yiq(a);
+    let (yb, ib, qb) = yiq(b);
+    let (dy, di, dq) = (ya - yb, ia - ib, qa - qb);
+    0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq
+}
+
+fn yiq(c: &Rgba<u8>) -> (f32, f32, f32) {
+    let [r, g, b] = blend_white(c);
+    (
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23,
+        r * 0.595_977_99 - g * 0.274_176_5 - b * 0.321_801_5,
+        r * 0.211_470_2 - g * 0.522_617_4 + b * 0.311_147_2,
+    )
+}
+
+fn luma(c: &Rgba<u8>) -> f32 {
+    yiq(c).0
+}
+
+fn blend_white(c: &Rgba<u8>) -> [f32; 3] {
+    let a = c[3] as f32 / 255.0;
+    let blend = |v: u8| 255.0 + (v as f32 - 255.0) * a;
+    [blend(c[0]), blend(c[1]), blend(c[2])]
+}
@@ -0,0 +1,69 @@
+use nannou::geom::{pt2, Point2, Rect};
+use nannou::App;
+
+use crate::render::Renderer;
+use crate::svg::Svg;
+
+/// What a sketch's scene depends on besides its model. This is taken from
+/// the `App` when running, or fixed when rendering offscreen so that the
+/// output is reproducible.
+#[derive(Clone, Copy, Debug)]
+pub struct Input {
+    pub window_rect: Rect,
+    pub time: f32,
+    pub mouse: Point2,
+    /// The seed for a sketch's random numbers, which is fixed so that the
+    /// same input always draws the same scene
+    pub seed: u64,
+}
+
+impl Input {
+    /// The window size is in points. The time, the mouse position and the
+    /// seed are zero unless specified.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            window_rect: Rect::from_w_h(width as f32, height as f32),
+            time: 0.0,
+            mouse: pt2(0.0, 0.0),
+            seed: 0,
+        }
+    }
+
+    pub fn from_app(app: &App) -> Self {
+        Self {
+            window_rect: app.window_rect(),
+            time: app.time,
+            mouse: pt2(app.mouse.x, app.mouse.y),
+            seed: 0,
+        }
+    }
+
+    pub fn time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn mouse(mut self, x: f32, y: f32) -> Self {
+        self.mouse = pt2(x, y);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// A renderer for an image of the same size as the window
+    pub fn renderer(&self) -> Renderer {
+        let w = self.window_rect.w().round() as u32;
+        let h = self.window_rect.h().round() as u32;
+        Renderer::new(w, h)
+    }
+
+    /// An SVG writer for a document of the same size as the window
+    pub fn svg(&self) -> Svg {
+        let w = self.window_rect.w().round() as u32;
+        let h = self.window_rect.h().round() as u32;
+        Svg::new(w, h)
+    }
+}
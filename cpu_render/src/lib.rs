@@ -0,0 +1,67 @@
+//! A software renderer for the kind of scenes the sketches in this repo draw
+//! with nannou's `Draw`. It needs neither a window nor a GPU, so a sketch's
+//! `view` can be rendered to a PNG on a headless machine.
+//!
+//! nannou doesn't expose the primitives recorded in its `Draw`, so this
+//! crate has its own [`Draw`] with the same builder methods for the
+//! primitives the sketches use (`background`, `rect`, `ellipse`, `line`,
+//! `polyline`, `polygon`, `path` and `mesh`). There's no text or textured
+//! mesh, as their pixels are only known on the GPU, so a scene that needs
+//! them fails to compile rather than rendering without them:
+//!
+//! ```no_run
+//! use nannou::prelude::*;
+//!
+//! let draw = cpu_render::Draw::new();
+//! draw.background().color(PLUM);
+//! draw.ellipse().x_y(100.0, 0.0).radius(50.0).color(STEELBLUE);
+//!
+//! let image = cpu_render::Renderer::new(640, 480).render(&draw);
+//! image.save("out.png").unwrap();
+//! ```
+//!
+//! To share the drawing code between the window and the renderer, a sketch
+//! draws its scene with this `Draw` and replays it with `to_nannou()` in its
+//! `view`:
+//!
+//! ```no_run
+//! use nannou::prelude::*;
+//! use cpu_render::Input;
+//!
+//! fn scene(draw: &cpu_render::Draw, input: &Input) {
+//!     draw.ellipse().x_y(input.mouse.x, input.mouse.y).color(STEELBLUE);
+//! }
+//!
+//! fn view(app: &App, frame: Frame) {
+//!     let scene_draw = cpu_render::Draw::new();
+//!     scene(&scene_draw, &Input::from_app(app));
+//!
+//!     let draw = app.draw();
+//!     scene_draw.to_nannou(&draw);
+//!     draw.to_frame(app, &frame).unwrap();
+//! }
+//! ```
+//!
+//! The same `Draw` can also be written as an SVG document with [`Svg`], or
+//! with [`save_svg`] from an event handler.
+
+mod draw;
+mod golden;
+mod input;
+mod path;
+mod primitive;
+mod raster;
+mod render;
+mod replay;
+mod stroke;
+mod svg;
+
+pub use draw::{Draw, Drawing, Transform};
+pub use golden::{assert_scene, Golden};
+pub use input::Input;
+pub use primitive::{
+    Background, Ellipse, Line, Mesh, PathFill, PathInit, PathStroke, Polygon, Polyline, Primitive,
+    Rect,
+};
+pub use render::Renderer;
+pub use svg::{save_svg, Svg};
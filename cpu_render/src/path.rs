@@ -0,0 +1,143 @@
+use nannou::geom::{pt2, Point2};
+use nannou::lyon::math::Point;
+use nannou::lyon::path::PathEvent;
+
+use std::f32::consts::PI;
+
+/// A flattened subpath
+pub(crate) struct Polyline {
+    pub points: Vec<Point2>,
+    pub closed: bool,
+}
+
+/// Flattens the curves into line segments so that they don't deviate from
+/// the curves by more than `tolerance`.
+pub(crate) fn flatten(events: &[PathEvent], tolerance: f32) -> Vec<Polyline> {
+    let mut polylines = vec![];
+    let mut points = vec![];
+
+    for event in events {
+        match *event {
+            PathEvent::Begin { at } => {
+                points.clear();
+                points.push(to_point2(at));
+            }
+            PathEvent::Line { to, .. } => points.push(to_point2(to)),
+            PathEvent::Quadratic { from, ctrl, to } => {
+                let (p0, p1, p2) = (to_point2(from), to_point2(ctrl), to_point2(to));
+                // Wang's formula
+                let dd = norm(add(sub(p0, mul(p1, 2.0)), p2));
+                let n = segments((0.25 * dd / tolerance).sqrt());
+                points.extend((1..=n).map(|i| {
+                    let t = i as f32 / n as f32;
+                    let mt = 1.0 - t;
+                    add(add(mul(p0, mt * mt), mul(p1, 2.0 * mt * t)), mul(p2, t * t))
+                }));
+            }
+            PathEvent::Cubic {
+                from,
+                ctrl1,
+                ctrl2,
+                to,
+            } => {
+                let (p0, p1, p2, p3) = (
+                    to_point2(from),
+                    to_point2(ctrl1),
+                    to_point2(ctrl2),
+                    to_point2(to),
+                );
+                let dd =
+                    norm(add(sub(p0, mul(p1, 2.0)), p2)).max(norm(add(sub(p1, mul(p2, 2.0)), p3)));
+                let n = segments((0.75 * dd / tolerance).sqrt());
+                points.extend((1..=n).map(|i| {
+                    let t = i as f32 / n as f32;
+                    let mt = 1.0 - t;
+                    add(
+                        add(mul(p0, mt * mt * mt), mul(p1, 3.0 * mt * mt * t)),
+                        add(mul(p2, 3.0 * mt * t * t), mul(p3, t * t * t)),
+                    )
+                }));
+            }
+            PathEvent::End { close, .. } => {
+                polylines.push(Polyline {
+                    points: std::mem::take(&mut points),
+                    closed: close,
+                });
+            }
+        }
+    }
+
+    // a subpath without the End event
+    if !points.is_empty() {
+        polylines.push(Polyline {
+            points,
+            closed: false,
+        });
+    }
+
+    polylines
+}
+
+/// The outline of an ellipse centered at the origin. If `resolution` is not
+/// specified, the number of vertices is decided by `tolerance`.
+pub(crate) fn ellipse(w: f32, h: f32, resolution: Option<usize>, tolerance: f32) -> Vec<Point2> {
+    let (rx, ry) = (w / 2.0, h / 2.0);
+    let n = resolution.unwrap_or_else(|| {
+        let r = rx.abs().max(ry.abs());
+        if r <= tolerance {
+            return 8;
+        }
+        // the angle of an arc whose sagitta is `tolerance`
+        let angle = 2.0 * (1.0 - tolerance / r).acos();
+        ((2.0 * PI / angle).ceil() as usize).clamp(8, 1024)
+    });
+
+    (0..n)
+        .map(|i| {
+            let theta = 2.0 * PI * i as f32 / n as f32;
+            pt2(rx * theta.cos(), ry * theta.sin())
+        })
+        .collect()
+}
+
+fn segments(n: f32) -> usize {
+    if n.is_finite() {
+        (n.ceil() as usize).clamp(1, 1024)
+    } else {
+        1
+    }
+}
+
+fn to_point2(p: Point) -> Point2 {
+    pt2(p.x, p.y)
+}
+
+// Small helpers so as not to depend on the operators of nannou's vector
+// types, which differ between versions.
+
+pub(crate) fn add(a: Point2, b: Point2) -> Point2 {
+    pt2(a.x + b.x, a.y + b.y)
+}
+
+pub(crate) fn sub(a: Point2, b: Point2) -> Point2 {
+    pt2(a.x - b.x, a.y - b.y)
+}
+
+pub(crate) fn mul(a: Point2, s: f32) -> Point2 {
+    pt2(a.x * s, a.y * s)
+}
+
+pub(crate) fn norm(a: Point2) -> f32 {
+    (a.x * a.x + a.y * a.y).sqrt()
+}
+
+pub(crate) fn signed_area(points: &[Point2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>()
+        / 2.0
+}
@@ -0,0 +1,467 @@
+use nannou::color::{IntoLinSrgba, LinSrgba};
+use nannou::geom::{pt2, vec2, Point2, Vector2};
+use nannou::lyon::math::point;
+use nannou::lyon::path::PathEvent;
+
+use crate::draw::{Draw, Drawing};
+
+// The same defaults as nannou's theme
+fn default_fill() -> LinSrgba {
+    LinSrgba::new(1.0, 1.0, 1.0, 1.0)
+}
+
+fn default_stroke() -> LinSrgba {
+    LinSrgba::new(0.0, 0.0, 0.0, 1.0)
+}
+
+// nannou draws rects and ellipses of this size when not specified
+const DEFAULT_SIZE: f32 = 100.0;
+
+/// All the primitives `Draw` can record.
+pub enum Primitive {
+    Background(Background),
+    Rect(Rect),
+    Ellipse(Ellipse),
+    Line(Line),
+    Polyline(Polyline),
+    Polygon(Polygon),
+    PathFill(PathFill),
+    PathStroke(PathStroke),
+    Mesh(Mesh),
+}
+
+macro_rules! impl_into_primitive {
+    ($($ty:ident),*) => {
+        $(
+            impl From<$ty> for Primitive {
+                fn from(p: $ty) -> Self {
+                    Primitive::$ty(p)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_primitive!(
+    Background, Rect, Ellipse, Line, Polyline, Polygon, PathFill, PathStroke, Mesh
+);
+
+// The position, size and colours shared by rects and ellipses
+pub(crate) struct Shape {
+    pub xy: Point2,
+    pub wh: Vector2,
+    pub rotation: f32,
+    pub fill: Option<LinSrgba>,
+    pub stroke: Option<LinSrgba>,
+    pub stroke_weight: f32,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Self {
+            xy: pt2(0.0, 0.0),
+            wh: vec2(DEFAULT_SIZE, DEFAULT_SIZE),
+            rotation: 0.0,
+            fill: Some(default_fill()),
+            stroke: None,
+            stroke_weight: 1.0,
+        }
+    }
+}
+
+macro_rules! impl_shape_methods {
+    ($($ty:ident),*) => {
+        $(
+            impl Drawing<$ty> {
+                pub fn x_y(self, x: f32, y: f32) -> Self {
+                    self.map(|p| p.shape.xy = pt2(x, y))
+                }
+
+                pub fn xy(self, xy: Point2) -> Self {
+                    self.map(|p| p.shape.xy = xy)
+                }
+
+                pub fn w_h(self, w: f32, h: f32) -> Self {
+                    self.map(|p| p.shape.wh = vec2(w, h))
+                }
+
+                pub fn wh(self, wh: Vector2) -> Self {
+                    self.map(|p| p.shape.wh = wh)
+                }
+
+                pub fn w(self, w: f32) -> Self {
+                    self.map(|p| p.shape.wh.x = w)
+                }
+
+                pub fn h(self, h: f32) -> Self {
+                    self.map(|p| p.shape.wh.y = h)
+                }
+
+                /// Rotates counterclockwise around its center.
+                pub fn rotate(self, radians: f32) -> Self {
+                    self.map(|p| p.shape.rotation = radians)
+                }
+
+                pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+                    self.map(|p| p.shape.fill = Some(color.into_lin_srgba()))
+                }
+
+                pub fn no_fill(self) -> Self {
+                    self.map(|p| p.shape.fill = None)
+                }
+
+                pub fn stroke<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+                    self.map(|p| p.shape.stroke = Some(color.into_lin_srgba()))
+                }
+
+                /// Also enables the stroke with the default colour if no
+                /// colour is specified, as nannou does.
+                pub fn stroke_weight(self, weight: f32) -> Self {
+                    self.map(|p| {
+                        p.shape.stroke_weight = weight;
+                        p.shape.stroke.get_or_insert_with(default_stroke);
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_shape_methods!(Rect, Ellipse);
+
+pub struct Background {
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self {
+            color: default_fill(),
+        }
+    }
+}
+
+impl Drawing<Background> {
+    pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+        self.map(|p| p.color = color.into_lin_srgba())
+    }
+}
+
+#[derive(Default)]
+pub struct Rect {
+    pub(crate) shape: Shape,
+}
+
+#[derive(Default)]
+pub struct Ellipse {
+    pub(crate) shape: Shape,
+    pub(crate) resolution: Option<usize>,
+}
+
+impl Drawing<Ellipse> {
+    pub fn radius(self, radius: f32) -> Self {
+        self.w_h(radius * 2.0, radius * 2.0)
+    }
+
+    /// The number of vertices. By default, this is decided by the size on
+    /// the image so that the outline looks smooth.
+    pub fn resolution(self, resolution: usize) -> Self {
+        self.map(|p| p.resolution = Some(resolution))
+    }
+}
+
+pub struct Line {
+    pub(crate) start: Point2,
+    pub(crate) end: Point2,
+    pub(crate) weight: f32,
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self {
+            start: pt2(0.0, 0.0),
+            end: pt2(0.0, 0.0),
+            weight: 1.0,
+            color: default_fill(),
+        }
+    }
+}
+
+impl Drawing<Line> {
+    pub fn start(self, start: Point2) -> Self {
+        self.map(|p| p.start = start)
+    }
+
+    pub fn end(self, end: Point2) -> Self {
+        self.map(|p| p.end = end)
+    }
+
+    pub fn points(self, start: Point2, end: Point2) -> Self {
+        self.start(start).end(end)
+    }
+
+    pub fn weight(self, weight: f32) -> Self {
+        self.map(|p| p.weight = weight)
+    }
+
+    pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+        self.map(|p| p.color = color.into_lin_srgba())
+    }
+}
+
+pub struct Polyline {
+    pub(crate) points: Vec<Point2>,
+    pub(crate) weight: f32,
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for Polyline {
+    fn default() -> Self {
+        Self {
+            points: vec![],
+            weight: 1.0,
+            color: default_fill(),
+        }
+    }
+}
+
+impl Drawing<Polyline> {
+    pub fn points<I: IntoIterator<Item = Point2>>(self, points: I) -> Self {
+        self.map(|p| p.points = points.into_iter().collect())
+    }
+
+    pub fn weight(self, weight: f32) -> Self {
+        self.map(|p| p.weight = weight)
+    }
+
+    pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+        self.map(|p| p.color = color.into_lin_srgba())
+    }
+}
+
+pub struct Polygon {
+    pub(crate) points: Vec<Point2>,
+    // Per-vertex colours, if specified by `points_colored()`
+    pub(crate) colors: Option<Vec<LinSrgba>>,
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for Polygon {
+    fn default() -> Self {
+        Self {
+            points: vec![],
+            colors: None,
+            color: default_fill(),
+        }
+    }
+}
+
+impl Drawing<Polygon> {
+    pub fn points<I: IntoIterator<Item = Point2>>(self, points: I) -> Self {
+        self.map(|p| {
+            p.points = points.into_iter().collect();
+            p.colors = None;
+        })
+    }
+
+    /// The colours are interpolated over a fan from the centroid, so a
+    /// concave polygon may look different from nannou's output.
+    pub fn points_colored<I, C>(self, points: I) -> Self
+    where
+        I: IntoIterator<Item = (Point2, C)>,
+        C: IntoLinSrgba<f32>,
+    {
+        let (points, colors) = points
+            .into_iter()
+            .map(|(p, c)| (p, c.into_lin_srgba()))
+            .unzip();
+        self.map(|p| {
+            p.points = points;
+            p.colors = Some(colors);
+        })
+    }
+
+    pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+        self.map(|p| p.color = color.into_lin_srgba())
+    }
+}
+
+/// Returned by `Draw::path()`. Like nannou, nothing is drawn until either
+/// `fill()` or `stroke()` is called.
+pub struct PathInit {
+    pub(crate) draw: Draw,
+}
+
+impl PathInit {
+    pub fn fill(self) -> Drawing<PathFill> {
+        Drawing::new(&self.draw, PathFill::default())
+    }
+
+    pub fn stroke(self) -> Drawing<PathStroke> {
+        Drawing::new(&self.draw, PathStroke::default())
+    }
+}
+
+/// Filled with the even-odd rule, which is the default of lyon's fill
+/// tessellator. Every subpath is closed implicitly.
+pub struct PathFill {
+    pub(crate) events: Vec<PathEvent>,
+    pub(crate) tolerance: Option<f32>,
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for PathFill {
+    fn default() -> Self {
+        Self {
+            events: vec![],
+            tolerance: None,
+            color: default_fill(),
+        }
+    }
+}
+
+pub struct PathStroke {
+    pub(crate) events: Vec<PathEvent>,
+    pub(crate) tolerance: Option<f32>,
+    pub(crate) weight: f32,
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for PathStroke {
+    fn default() -> Self {
+        Self {
+            events: vec![],
+            tolerance: None,
+            weight: 1.0,
+            color: default_fill(),
+        }
+    }
+}
+
+// Converts points to the events of a single subpath
+fn point_events<I: IntoIterator<Item = Point2>>(points: I, close: bool) -> Vec<PathEvent> {
+    let points: Vec<_> = points.into_iter().map(|p| point(p.x, p.y)).collect();
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return vec![],
+    };
+
+    let mut events = vec![PathEvent::Begin { at: first }];
+    events.extend(points.windows(2).map(|w| PathEvent::Line {
+        from: w[0],
+        to: w[1],
+    }));
+    events.push(PathEvent::End { last, first, close });
+    events
+}
+
+macro_rules! impl_path_methods {
+    ($($ty:ident),*) => {
+        $(
+            impl Drawing<$ty> {
+                pub fn events<I: IntoIterator<Item = PathEvent>>(self, events: I) -> Self {
+                    self.map(|p| p.events = events.into_iter().collect())
+                }
+
+                pub fn points<I: IntoIterator<Item = Point2>>(self, points: I) -> Self {
+                    self.map(|p| p.events = point_events(points, false))
+                }
+
+                pub fn points_closed<I: IntoIterator<Item = Point2>>(self, points: I) -> Self {
+                    self.map(|p| p.events = point_events(points, true))
+                }
+
+                /// The maximum distance between the curves and their
+                /// flattened polylines, in the units of the drawing.
+                pub fn tolerance(self, tolerance: f32) -> Self {
+                    self.map(|p| p.tolerance = Some(tolerance))
+                }
+
+                pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+                    self.map(|p| p.color = color.into_lin_srgba())
+                }
+            }
+        )*
+    };
+}
+
+impl_path_methods!(PathFill, PathStroke);
+
+impl Drawing<PathStroke> {
+    pub fn weight(self, weight: f32) -> Self {
+        self.map(|p| p.weight = weight)
+    }
+}
+
+/// A triangle mesh. Each vertex has its own colour or falls back to the
+/// mesh's colour.
+pub struct Mesh {
+    pub(crate) points: Vec<Point2>,
+    pub(crate) colors: Option<Vec<LinSrgba>>,
+    pub(crate) indices: Vec<usize>,
+    pub(crate) color: LinSrgba,
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Self {
+            points: vec![],
+            colors: None,
+            indices: vec![],
+            color: default_fill(),
+        }
+    }
+}
+
+impl Drawing<Mesh> {
+    /// Every three points make a triangle.
+    pub fn points<I: IntoIterator<Item = Point2>>(self, points: I) -> Self {
+        let points: Vec<_> = points.into_iter().collect();
+        let indices: Vec<usize> = (0..points.len()).collect();
+        self.indexed(points, indices)
+    }
+
+    pub fn points_colored<I, C>(self, points: I) -> Self
+    where
+        I: IntoIterator<Item = (Point2, C)>,
+        C: IntoLinSrgba<f32>,
+    {
+        let points: Vec<_> = points.into_iter().collect();
+        let indices = (0..points.len()).collect::<Vec<_>>();
+        self.indexed_colored(points, indices)
+    }
+
+    pub fn indexed<I, J>(self, points: I, indices: J) -> Self
+    where
+        I: IntoIterator<Item = Point2>,
+        J: IntoIterator<Item = usize>,
+    {
+        self.map(|p| {
+            p.points = points.into_iter().collect();
+            p.colors = None;
+            p.indices = indices.into_iter().collect();
+        })
+    }
+
+    pub fn indexed_colored<I, C, J>(self, points: I, indices: J) -> Self
+    where
+        I: IntoIterator<Item = (Point2, C)>,
+        C: IntoLinSrgba<f32>,
+        J: IntoIterator<Item = usize>,
+    {
+        let (points, colors) = points
+            .into_iter()
+            .map(|(p, c)| (p, c.into_lin_srgba()))
+            .unzip();
+        self.map(|p| {
+            p.points = points;
+            p.colors = Some(colors);
+            p.indices = indices.into_iter().collect();
+        })
+    }
+
+    pub fn color<C: IntoLinSrgba<f32>>(self, color: C) -> Self {
+        self.map(|p| p.color = color.into_lin_srgba())
+    }
+}
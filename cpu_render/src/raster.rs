@@ -0,0 +1,481 @@
+use nannou::color::LinSrgba;
+use nannou::geom::Point2;
+use nannou::image::{Rgba, RgbaImage};
+
+use std::cmp::Ordering;
+
+// The number of pixel rows rasterized at once by `fill_triangles()`, which
+// bounds the memory for the samples
+const BAND_HEIGHT: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FillRule {
+    EvenOdd,
+    NonZero,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+/// A colour in linear RGB, premultiplied by the alpha
+pub(crate) type Premultiplied = [f32; 4];
+
+pub(crate) fn premultiply(color: LinSrgba) -> Premultiplied {
+    let (r, g, b, a) = color.into_components();
+    [r * a, g * a, b * a, a]
+}
+
+// Source over
+fn blend(dst: &mut Premultiplied, src: Premultiplied) {
+    let k = 1.0 - src[3];
+    for (dst, src) in dst.iter_mut().zip(src.iter()) {
+        *dst = src + *dst * k;
+    }
+}
+
+struct Edge {
+    // y0 < y1
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+impl Edge {
+    fn new(a: Point2, b: Point2) -> Option<Self> {
+        if a.y == b.y || !(a.x.is_finite() && a.y.is_finite() && b.x.is_finite() && b.y.is_finite())
+        {
+            return None;
+        }
+
+        let (a, b, winding) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+        Some(Self {
+            x0: a.x,
+            y0: a.y,
+            x1: b.x,
+            y1: b.y,
+            winding,
+        })
+    }
+
+    fn x_at(&self, y: f32) -> f32 {
+        self.x0 + (y - self.y0) * (self.x1 - self.x0) / (self.y1 - self.y0)
+    }
+}
+
+/// The pixels of the image being rendered, with `samples` x `samples`
+/// samples per pixel for anti-aliasing. Coordinates are in pixels with the
+/// origin at the top left corner.
+pub(crate) struct Canvas {
+    width: usize,
+    height: usize,
+    samples: usize,
+    pixels: Vec<Premultiplied>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, samples: u32) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        Self {
+            width,
+            height,
+            samples: samples.max(1) as usize,
+            pixels: vec![[0.0; 4]; width * height],
+        }
+    }
+
+    pub fn clear(&mut self, color: Premultiplied) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = color;
+        }
+    }
+
+    /// Fills the area enclosed by the polygons with a single colour.
+    pub fn fill(&mut self, polygons: &[Vec<Point2>], rule: FillRule, color: Premultiplied) {
+        let (x0, y0, x1, y1) = match self.bounds(polygons.iter().flatten()) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let edges: Vec<Edge> = polygons
+            .iter()
+            .flat_map(|polygon| {
+                let n = polygon.len();
+                (0..n).filter_map(move |i| Edge::new(polygon[i], polygon[(i + 1) % n]))
+            })
+            .collect();
+
+        let s = self.samples;
+        let mut counts = vec![0; x1 - x0];
+        let mut crossings = vec![];
+
+        for py in y0..y1 {
+            counts.iter_mut().for_each(|c| *c = 0);
+
+            for j in 0..s {
+                let sy = py as f32 + (j as f32 + 0.5) / s as f32;
+                crossings.clear();
+                crossings.extend(
+                    edges
+                        .iter()
+                        .filter(|e| e.y0 <= sy && sy < e.y1)
+                        .map(|e| (e.x_at(sy), e.winding)),
+                );
+                crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    if !rule.is_inside(winding) {
+                        continue;
+                    }
+
+                    // the sample columns whose centers are in the span
+                    let first = sample_index(pair[0].0, s).max(x0 * s);
+                    let last = sample_index(pair[1].0, s).min(x1 * s);
+                    for k in first..last {
+                        counts[k / s - x0] += 1;
+                    }
+                }
+            }
+
+            let total = (s * s) as f32;
+            for (i, &count) in counts.iter().enumerate() {
+                if count > 0 {
+                    let coverage = count as f32 / total;
+                    let src = [
+                        color[0] * coverage,
+                        color[1] * coverage,
+                        color[2] * coverage,
+                        color[3] * coverage,
+                    ];
+                    blend(&mut self.pixels[py * self.width + x0 + i], src);
+                }
+            }
+        }
+    }
+
+    /// Fills the triangles, interpolating the colours of their vertices.
+    /// The triangles are blended in order per sample, so they may overlap.
+    pub fn fill_triangles(&mut self, triangles: &[[(Point2, LinSrgba); 3]]) {
+        let (x0, y0, x1, y1) = match self.bounds(triangles.iter().flatten().map(|(p, _)| p)) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let s = self.samples;
+        let stride = (x1 - x0) * s;
+        let mut samples = vec![[0.0; 4]; stride * BAND_HEIGHT * s];
+
+        let mut band_y0 = y0;
+        while band_y0 < y1 {
+            let band_y1 = (band_y0 + BAND_HEIGHT).min(y1);
+            samples.iter_mut().for_each(|c| *c = [0.0; 4]);
+
+            for tri in triangles {
+                rasterize_triangle(tri, s, (x0, band_y0, x1, band_y1), &mut samples);
+            }
+
+            // resolve the samples
+            let total = (s * s) as f32;
+            for py in band_y0..band_y1 {
+                for px in x0..x1 {
+                    let mut sum = [0.0; 4];
+                    for j in 0..s {
+                        let row = ((py - band_y0) * s + j) * stride;
+                        for i in 0..s {
+                            let c = samples[row + (px - x0) * s + i];
+                            for (sum, c) in sum.iter_mut().zip(c.iter()) {
+                                *sum += c;
+                            }
+                        }
+                    }
+                    if sum[3] > 0.0 {
+                        let src = [
+                            sum[0] / total,
+                            sum[1] / total,
+                            sum[2] / total,
+                            sum[3] / total,
+                        ];
+                        blend(&mut self.pixels[py * self.width + px], src);
+                    }
+                }
+            }
+
+            band_y0 = band_y1;
+        }
+    }
+
+    /// Converts the pixels to sRGB.
+    pub fn to_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let [r, g, b, a] = self.pixels[y as usize * self.width + x as usize];
+            if a <= 0.0 {
+                return Rgba([0, 0, 0, 0]);
+            }
+            Rgba([
+                to_srgb_u8(r / a),
+                to_srgb_u8(g / a),
+                to_srgb_u8(b / a),
+                (a.min(1.0) * 255.0).round() as u8,
+            ])
+        })
+    }
+
+    // The pixel range covering the points, clipped to the canvas
+    fn bounds<'a, I: Iterator<Item = &'a Point2>>(
+        &self,
+        points: I,
+    ) -> Option<(usize, usize, usize, usize)> {
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for p in points.filter(|p| p.x.is_finite() && p.y.is_finite()) {
+            min_x = min_x.min(p.x);
+            min_y = min_y.min(p.y);
+            max_x = max_x.max(p.x);
+            max_y = max_y.max(p.y);
+        }
+
+        let x0 = min_x.floor().max(0.0) as usize;
+        let y0 = min_y.floor().max(0.0) as usize;
+        let x1 = (max_x.ceil().max(0.0) as usize).min(self.width);
+        let y1 = (max_y.ceil().max(0.0) as usize).min(self.height);
+        if min_x > max_x || x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        Some((x0, y0, x1, y1))
+    }
+}
+
+// The index of the first sample whose center is at or after `x`
+fn sample_index(x: f32, samples: usize) -> usize {
+    (x * samples as f32 - 0.5).ceil().max(0.0) as usize
+}
+
+fn rasterize_triangle(
+    tri: &[(Point2, LinSrgba); 3],
+    s: usize,
+    (x0, y0, x1, y1): (usize, usize, usize, usize),
+    samples: &mut [Premultiplied],
+) {
+    let edge =
+        |a: Point2, b: Point2, x: f32, y: f32| (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+
+    // make the vertices clockwise on the screen so that the area is positive
+    let (mut v0, mut v1, v2) = (tri[0], tri[1], tri[2]);
+    let mut area = edge(v0.0, v1.0, v2.0.x, v2.0.y);
+    if area < 0.0 {
+        std::mem::swap(&mut v0, &mut v1);
+        area = -area;
+    }
+    if area == 0.0 || area.is_nan() {
+        return;
+    }
+    let (p0, p1, p2) = (v0.0, v1.0, v2.0);
+
+    // Samples exactly on an edge shared by two triangles should be covered
+    // by only one of them.
+    let is_top_left = |a: Point2, b: Point2| (a.y == b.y && b.x < a.x) || b.y < a.y;
+    let inside = |w: f32, a: Point2, b: Point2| w > 0.0 || (w == 0.0 && is_top_left(a, b));
+
+    let min_x = p0.x.min(p1.x).min(p2.x);
+    let max_x = p0.x.max(p1.x).max(p2.x);
+    let min_y = p0.y.min(p1.y).min(p2.y);
+    let max_y = p0.y.max(p1.y).max(p2.y);
+
+    let stride = (x1 - x0) * s;
+    let i_start = sample_index(min_x, s).max(x0 * s);
+    let i_end = sample_index(max_x, s).min(x1 * s);
+    let j_start = sample_index(min_y, s).max(y0 * s);
+    let j_end = sample_index(max_y, s).min(y1 * s);
+
+    let colors = [premultiply(v0.1), premultiply(v1.1), premultiply(v2.1)];
+
+    for j in j_start..j_end {
+        let y = (j as f32 + 0.5) / s as f32;
+        for i in i_start..i_end {
+            let x = (i as f32 + 0.5) / s as f32;
+
+            let w0 = edge(p1, p2, x, y);
+            let w1 = edge(p2, p0, x, y);
+            let w2 = edge(p0, p1, x, y);
+            if !(inside(w0, p1, p2) && inside(w1, p2, p0) && inside(w2, p0, p1)) {
+                continue;
+            }
+
+            let (l0, l1, l2) = (w0 / area, w1 / area, w2 / area);
+            let mut src = [0.0; 4];
+            for (k, c) in src.iter_mut().enumerate() {
+                *c = colors[0][k] * l0 + colors[1][k] * l1 + colors[2][k] * l2;
+            }
+            blend(&mut samples[(j - y0 * s) * stride + i - x0 * s], src);
+        }
+    }
+}
+
+pub(crate) fn to_srgb_u8(linear: f32) -> u8 {
+    let c = linear.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::geom::pt2;
+
+    fn red() -> LinSrgba {
+        LinSrgba::new(1.0, 0.0, 0.0, 1.0)
+    }
+
+    // Two triangles covering the rectangle from (x0, y0) to (x1, y1)
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32, color: LinSrgba) -> Vec<[(Point2, LinSrgba); 3]> {
+        let (a, b, c, d) = (pt2(x0, y0), pt2(x1, y0), pt2(x1, y1), pt2(x0, y1));
+        vec![
+            [(a, color), (b, color), (c, color)],
+            [(a, color), (c, color), (d, color)],
+        ]
+    }
+
+    fn pixel(canvas: &Canvas, x: usize, y: usize) -> Premultiplied {
+        canvas.pixels[y * canvas.width + x]
+    }
+
+    fn assert_pixel(canvas: &Canvas, x: usize, y: usize, expected: Premultiplied) {
+        let actual = pixel(canvas, x, y);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!(
+                (a - e).abs() < 1e-5,
+                "({}, {}): {:?} != {:?}",
+                x,
+                y,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn rect_covers_its_pixels() {
+        let mut canvas = Canvas::new(4, 4, 4);
+        canvas.fill_triangles(&rect(1.0, 1.0, 3.0, 3.0, red()));
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let inside = (1..3).contains(&x) && (1..3).contains(&y);
+                let expected = if inside {
+                    [1.0, 0.0, 0.0, 1.0]
+                } else {
+                    [0.0; 4]
+                };
+                assert_pixel(&canvas, x, y, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_covers_half_of_a_square() {
+        let mut canvas = Canvas::new(8, 8, 4);
+        let tri = [
+            (pt2(0.0, 0.0), red()),
+            (pt2(8.0, 0.0), red()),
+            (pt2(0.0, 8.0), red()),
+        ];
+        canvas.fill_triangles(&[tri]);
+
+        let coverage: f32 = canvas.pixels.iter().map(|p| p[3]).sum();
+        // less the samples exactly on the diagonal
+        assert!((coverage - 32.0).abs() <= 1.0, "{}", coverage);
+        // the pixels on the diagonal are partly covered
+        assert_pixel(&canvas, 3, 4, [0.375, 0.0, 0.0, 0.375]);
+        assert_pixel(&canvas, 0, 0, [1.0, 0.0, 0.0, 1.0]);
+        assert_pixel(&canvas, 7, 7, [0.0; 4]);
+    }
+
+    #[test]
+    fn edges_are_antialiased() {
+        let mut canvas = Canvas::new(4, 1, 4);
+        // half of the second pixel and a quarter of the third
+        canvas.fill_triangles(&rect(1.5, 0.0, 2.25, 1.0, red()));
+
+        assert_pixel(&canvas, 0, 0, [0.0; 4]);
+        assert_pixel(&canvas, 1, 0, [0.5, 0.0, 0.0, 0.5]);
+        assert_pixel(&canvas, 2, 0, [0.25, 0.0, 0.0, 0.25]);
+        assert_pixel(&canvas, 3, 0, [0.0; 4]);
+
+        // without anti-aliasing, a pixel is covered if its center is
+        let mut canvas = Canvas::new(4, 1, 1);
+        canvas.fill_triangles(&rect(1.5, 0.0, 2.25, 1.0, red()));
+        assert_pixel(&canvas, 1, 0, [1.0, 0.0, 0.0, 1.0]);
+        assert_pixel(&canvas, 2, 0, [0.0; 4]);
+    }
+
+    #[test]
+    fn shared_edges_are_covered_once() {
+        let mut canvas = Canvas::new(4, 4, 4);
+        let color = LinSrgba::new(1.0, 0.0, 0.0, 0.5);
+        canvas.fill_triangles(&rect(0.0, 0.0, 4.0, 4.0, color));
+
+        // blending the diagonal twice would make it more opaque
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_pixel(&canvas, x, y, [0.5, 0.0, 0.0, 0.5]);
+            }
+        }
+    }
+
+    #[test]
+    fn colors_are_interpolated() {
+        let mut canvas = Canvas::new(2, 1, 1);
+        let (black, white) = (
+            LinSrgba::new(0.0, 0.0, 0.0, 1.0),
+            LinSrgba::new(1.0, 1.0, 1.0, 1.0),
+        );
+        let (a, b, c, d) = (pt2(0.0, 0.0), pt2(2.0, 0.0), pt2(2.0, 1.0), pt2(0.0, 1.0));
+        canvas.fill_triangles(&[
+            [(a, black), (b, white), (c, white)],
+            [(a, black), (c, white), (d, black)],
+        ]);
+
+        // the centers are at a quarter and three quarters of the way
+        assert_pixel(&canvas, 0, 0, [0.25, 0.25, 0.25, 1.0]);
+        assert_pixel(&canvas, 1, 0, [0.75, 0.75, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn translucent_colors_are_blended_over() {
+        let mut canvas = Canvas::new(1, 1, 2);
+        canvas.clear(premultiply(LinSrgba::new(0.0, 0.0, 1.0, 1.0)));
+        canvas.fill_triangles(&rect(0.0, 0.0, 1.0, 1.0, LinSrgba::new(1.0, 0.0, 0.0, 0.5)));
+        assert_pixel(&canvas, 0, 0, [0.5, 0.0, 0.5, 1.0]);
+
+        // later triangles are blended over the earlier ones
+        let mut canvas = Canvas::new(1, 1, 2);
+        let mut triangles = rect(0.0, 0.0, 1.0, 1.0, LinSrgba::new(0.0, 1.0, 0.0, 1.0));
+        triangles.extend(rect(0.0, 0.0, 1.0, 1.0, LinSrgba::new(1.0, 0.0, 0.0, 0.25)));
+        canvas.fill_triangles(&triangles);
+        assert_pixel(&canvas, 0, 0, [0.25, 0.75, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn image_is_srgb() {
+        let mut canvas = Canvas::new(2, 1, 1);
+        canvas.fill_triangles(&rect(0.0, 0.0, 1.0, 1.0, LinSrgba::new(0.5, 0.0, 1.0, 0.5)));
+
+        let image = canvas.to_image();
+        // unpremultiplied and gamma encoded
+        assert_eq!(image.get_pixel(0, 0), &Rgba([188, 0, 255, 128]));
+        assert_eq!(image.get_pixel(1, 0), &Rgba([0, 0, 0, 0]));
+    }
+}
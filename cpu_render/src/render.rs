@@ -0,0 +1,233 @@
+use nannou::color::LinSrgba;
+use nannou::geom::{pt2, Point2, Rect};
+use nannou::image::RgbaImage;
+
+use crate::draw::{Draw, Transform};
+use crate::path::{self, add, mul};
+use crate::primitive::{Primitive, Shape};
+use crate::raster::{premultiply, Canvas, FillRule};
+use crate::stroke::stroke;
+
+// The maximum distance in pixels between curves and their flattened polylines
+const TOLERANCE: f32 = 0.1;
+
+/// Renders a `Draw` to an image on the CPU. The coordinates are the same as
+/// nannou's window: the origin is at the center and the y axis points up.
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    samples: u32,
+}
+
+impl Renderer {
+    /// `width` and `height` are in points, like the size of a window.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            scale_factor: 1.0,
+            samples: 4,
+        }
+    }
+
+    /// The number of pixels per point, e.g. 2.0 for a HiDPI display
+    pub fn scale_factor(mut self, scale_factor: f32) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    /// The number of samples per pixel is the square of this. 1 disables
+    /// anti-aliasing.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// The same as `app.window_rect()` for a window of this size
+    pub fn rect(&self) -> Rect {
+        Rect::from_w_h(self.width as f32, self.height as f32)
+    }
+
+    pub fn render(&self, draw: &Draw) -> RgbaImage {
+        let width = (self.width as f32 * self.scale_factor).round() as u32;
+        let height = (self.height as f32 * self.scale_factor).round() as u32;
+        let mut canvas = Canvas::new(width, height, self.samples);
+
+        let to_pixels = Transform::new(
+            self.scale_factor,
+            0.0,
+            0.0,
+            -self.scale_factor,
+            width as f32 / 2.0,
+            height as f32 / 2.0,
+        );
+
+        for command in draw.commands().iter() {
+            let transform = to_pixels.then(&command.transform);
+            let tolerance = TOLERANCE / transform.scale_factor().max(f32::EPSILON);
+            render_primitive(&mut canvas, &command.primitive, &transform, tolerance);
+        }
+
+        canvas.to_image()
+    }
+}
+
+fn render_primitive(
+    canvas: &mut Canvas,
+    primitive: &Primitive,
+    transform: &Transform,
+    tolerance: f32,
+) {
+    match primitive {
+        Primitive::Background(background) => canvas.clear(premultiply(background.color)),
+        Primitive::Rect(rect) => {
+            let (hw, hh) = (rect.shape.wh.x / 2.0, rect.shape.wh.y / 2.0);
+            let outline = vec![pt2(-hw, -hh), pt2(hw, -hh), pt2(hw, hh), pt2(-hw, hh)];
+            render_shape(canvas, &rect.shape, &outline, transform);
+        }
+        Primitive::Ellipse(ellipse) => {
+            let wh = ellipse.shape.wh;
+            let outline = path::ellipse(wh.x, wh.y, ellipse.resolution, tolerance);
+            render_shape(canvas, &ellipse.shape, &outline, transform);
+        }
+        Primitive::Line(line) => {
+            let polygons = stroke(&[line.start, line.end], false, line.weight);
+            fill(canvas, &polygons, FillRule::NonZero, line.color, transform);
+        }
+        Primitive::Polyline(polyline) => {
+            let polygons = stroke(&polyline.points, false, polyline.weight);
+            fill(
+                canvas,
+                &polygons,
+                FillRule::NonZero,
+                polyline.color,
+                transform,
+            );
+        }
+        Primitive::Polygon(polygon) => match &polygon.colors {
+            Some(colors) => {
+                let triangles = fan(&polygon.points, colors, transform);
+                canvas.fill_triangles(&triangles);
+            }
+            None => {
+                let polygons = [polygon.points.clone()];
+                fill(
+                    canvas,
+                    &polygons,
+                    FillRule::EvenOdd,
+                    polygon.color,
+                    transform,
+                );
+            }
+        },
+        Primitive::PathFill(path) => {
+            let tolerance = path.tolerance.unwrap_or(tolerance);
+            let polygons: Vec<_> = path::flatten(&path.events, tolerance)
+                .into_iter()
+                .map(|polyline| polyline.points)
+                .collect();
+            fill(canvas, &polygons, FillRule::EvenOdd, path.color, transform);
+        }
+        Primitive::PathStroke(path) => {
+            let tolerance = path.tolerance.unwrap_or(tolerance);
+            let polygons: Vec<_> = path::flatten(&path.events, tolerance)
+                .iter()
+                .flat_map(|polyline| stroke(&polyline.points, polyline.closed, path.weight))
+                .collect();
+            fill(canvas, &polygons, FillRule::NonZero, path.color, transform);
+        }
+        Primitive::Mesh(mesh) => {
+            let vertex = |i: usize| {
+                let color = mesh.colors.as_ref().map_or(mesh.color, |colors| colors[i]);
+                (transform.apply(mesh.points[i]), color)
+            };
+            let triangles: Vec<_> = mesh
+                .indices
+                .chunks_exact(3)
+                .filter(|tri| tri.iter().all(|&i| i < mesh.points.len()))
+                .map(|tri| [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])])
+                .collect();
+            canvas.fill_triangles(&triangles);
+        }
+    }
+}
+
+// Rects and ellipses, whose outline is given relative to their center
+fn render_shape(canvas: &mut Canvas, shape: &Shape, outline: &[Point2], transform: &Transform) {
+    let transform = transform
+        .then(&Transform::translation(shape.xy.x, shape.xy.y))
+        .then(&Transform::rotation(shape.rotation));
+
+    if let Some(color) = shape.fill {
+        fill(
+            canvas,
+            &[outline.to_vec()],
+            FillRule::NonZero,
+            color,
+            &transform,
+        );
+    }
+
+    if let Some(color) = shape.stroke {
+        let polygons = stroke(outline, true, shape.stroke_weight);
+        fill(canvas, &polygons, FillRule::NonZero, color, &transform);
+    }
+}
+
+fn fill(
+    canvas: &mut Canvas,
+    polygons: &[Vec<Point2>],
+    rule: FillRule,
+    color: LinSrgba,
+    transform: &Transform,
+) {
+    let polygons: Vec<Vec<Point2>> = polygons
+        .iter()
+        .map(|polygon| polygon.iter().map(|&p| transform.apply(p)).collect())
+        .collect();
+    canvas.fill(&polygons, rule, premultiply(color));
+}
+
+// Triangulates a polygon with per-vertex colours into a fan around the
+// centroid, which gets the average colour.
+pub(crate) fn fan(
+    points: &[Point2],
+    colors: &[LinSrgba],
+    transform: &Transform,
+) -> Vec<[(Point2, LinSrgba); 3]> {
+    let n = points.len().min(colors.len());
+    if n < 3 {
+        return vec![];
+    }
+
+    let center = mul(
+        points[..n]
+            .iter()
+            .fold(pt2(0.0, 0.0), |sum, &p| add(sum, p)),
+        1.0 / n as f32,
+    );
+    let center_color = {
+        let sum = colors[..n].iter().fold([0.0; 4], |mut sum, c| {
+            let (r, g, b, a) = c.into_components();
+            for (s, v) in sum.iter_mut().zip([r, g, b, a].iter()) {
+                *s += v;
+            }
+            sum
+        });
+        let k = 1.0 / n as f32;
+        LinSrgba::new(sum[0] * k, sum[1] * k, sum[2] * k, sum[3] * k)
+    };
+
+    let center = (transform.apply(center), center_color);
+    (0..n)
+        .map(|i| {
+            let j = (i + 1) % n;
+            [
+                center,
+                (transform.apply(points[i]), colors[i]),
+                (transform.apply(points[j]), colors[j]),
+            ]
+        })
+        .collect()
+}
@@ -0,0 +1,121 @@
+use nannou::color::LinSrgba;
+use nannou::geom::pt3;
+use nannou::math::Matrix4;
+
+use crate::draw::{Draw, Transform};
+use crate::primitive::{Primitive, Shape};
+
+impl Draw {
+    /// Draws the recorded primitives with nannou's `Draw`, so that the same
+    /// scene can be shown in a window and rendered by `Renderer`.
+    pub fn to_nannou(&self, draw: &nannou::Draw) {
+        for command in self.commands().iter() {
+            let draw = if command.transform == Transform::identity() {
+                draw.clone()
+            } else {
+                draw.transform(to_matrix(&command.transform))
+            };
+            replay(&draw, &command.primitive);
+        }
+    }
+}
+
+// column-major
+#[rustfmt::skip]
+fn to_matrix(t: &Transform) -> Matrix4<f32> {
+    Matrix4::new(
+        t.a, t.b, 0.0, 0.0,
+        t.c, t.d, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        t.e, t.f, 0.0, 1.0,
+    )
+}
+
+fn replay(draw: &nannou::Draw, primitive: &Primitive) {
+    match primitive {
+        Primitive::Background(background) => {
+            draw.background().color(background.color);
+        }
+        Primitive::Rect(rect) => {
+            let shape = &rect.shape;
+            let drawing = draw
+                .rect()
+                .xy(shape.xy)
+                .wh(shape.wh)
+                .rotate(shape.rotation)
+                .color(fill_color(shape));
+            if let Some(stroke) = shape.stroke {
+                drawing.stroke(stroke).stroke_weight(shape.stroke_weight);
+            }
+        }
+        Primitive::Ellipse(ellipse) => {
+            let shape = &ellipse.shape;
+            let mut drawing = draw
+                .ellipse()
+                .xy(shape.xy)
+                .wh(shape.wh)
+                .rotate(shape.rotation)
+                .color(fill_color(shape));
+            if let Some(resolution) = ellipse.resolution {
+                drawing = drawing.resolution(resolution);
+            }
+            if let Some(stroke) = shape.stroke {
+                drawing.stroke(stroke).stroke_weight(shape.stroke_weight);
+            }
+        }
+        Primitive::Line(line) => {
+            draw.line()
+                .start(line.start)
+                .end(line.end)
+                .weight(line.weight)
+                .color(line.color);
+        }
+        Primitive::Polyline(polyline) => {
+            draw.polyline()
+                .weight(polyline.weight)
+                .points(polyline.points.iter().cloned())
+                .color(polyline.color);
+        }
+        Primitive::Polygon(polygon) => match &polygon.colors {
+            Some(colors) => {
+                let points = polygon.points.iter().cloned().zip(colors.iter().cloned());
+                draw.polygon().points_colored(points);
+            }
+            None => {
+                draw.polygon()
+                    .color(polygon.color)
+                    .points(polygon.points.iter().cloned());
+            }
+        },
+        Primitive::PathFill(path) => {
+            let mut drawing = draw.path().fill().color(path.color);
+            if let Some(tolerance) = path.tolerance {
+                drawing = drawing.tolerance(tolerance);
+            }
+            drawing.events(path.events.iter().cloned());
+        }
+        Primitive::PathStroke(path) => {
+            let mut drawing = draw.path().stroke().weight(path.weight).color(path.color);
+            if let Some(tolerance) = path.tolerance {
+                drawing = drawing.tolerance(tolerance);
+            }
+            drawing.events(path.events.iter().cloned());
+        }
+        Primitive::Mesh(mesh) => {
+            let vertices = mesh.points.iter().enumerate().map(|(i, p)| {
+                let color = mesh.colors.as_ref().map_or(mesh.color, |colors| colors[i]);
+                (pt3(p.x, p.y, 0.0), color)
+            });
+            draw.mesh()
+                .indexed_colored(vertices, mesh.indices.iter().cloned());
+        }
+    }
+}
+
+// nannou always fills rects and ellipses, so use a transparent colour for
+// `no_fill()`.
+fn fill_color(shape: &Shape) -> LinSrgba {
+    shape
+        .fill
+        .unwrap_or_else(|| LinSrgba::new(0.0, 0.0, 0.0, 0.0))
+}
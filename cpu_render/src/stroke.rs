@@ -0,0 +1,82 @@
+use nannou::geom::{pt2, Point2};
+
+use crate::path::{add, mul, norm, signed_area, sub};
+
+// The same as the default of lyon's stroke tessellator
+const MITER_LIMIT: f32 = 4.0;
+
+/// Converts a stroke into polygons whose union is the stroke, with butt caps
+/// and miter joins (falling back to bevel joins beyond the miter limit).
+///
+/// All the polygons are counterclockwise so that they are merged correctly
+/// by the non-zero fill rule.
+pub(crate) fn stroke(points: &[Point2], closed: bool, weight: f32) -> Vec<Vec<Point2>> {
+    let hw = weight / 2.0;
+
+    let mut points: Vec<Point2> = points.to_vec();
+    points.dedup_by(|a, b| norm(sub(*a, *b)) < f32::EPSILON);
+    if closed && points.len() > 2 && norm(sub(points[0], points[points.len() - 1])) < f32::EPSILON {
+        points.pop();
+    }
+    if points.len() < 2 || hw <= 0.0 {
+        return vec![];
+    }
+
+    let n = points.len();
+    let n_segments = if closed { n } else { n - 1 };
+    let segment = |i: usize| (points[i % n], points[(i + 1) % n]);
+
+    let mut polygons = vec![];
+
+    for i in 0..n_segments {
+        let (a, b) = segment(i);
+        let o = offset(a, b, hw);
+        polygons.push(vec![add(a, o), sub(a, o), sub(b, o), add(b, o)]);
+    }
+
+    // the joins between the segment i - 1 and the segment i
+    let joins = if closed { 0..n } else { 1..n - 1 };
+    for i in joins {
+        let (a, v) = segment((i + n - 1) % n);
+        let (_, b) = segment(i);
+        let (o0, o1) = (offset(a, v, hw), offset(v, b, hw));
+
+        let d0 = sub(v, a);
+        let d1 = sub(b, v);
+        let cross = d0.x * d1.y - d0.y * d1.x;
+        if cross.abs() < f32::EPSILON {
+            continue;
+        }
+
+        // the outer side of the turn
+        let (o0, o1) = if cross > 0.0 {
+            (mul(o0, -1.0), mul(o1, -1.0))
+        } else {
+            (o0, o1)
+        };
+
+        let bisector = add(o0, o1);
+        let cos_half = norm(bisector) / (2.0 * hw);
+        if cos_half > 1.0 / MITER_LIMIT {
+            let tip = add(v, mul(bisector, 1.0 / (2.0 * cos_half * cos_half)));
+            polygons.push(vec![v, add(v, o0), tip, add(v, o1)]);
+        } else {
+            polygons.push(vec![v, add(v, o0), add(v, o1)]);
+        }
+    }
+
+    for polygon in polygons.iter_mut() {
+        if signed_area(polygon) < 0.0 {
+            polygon.reverse();
+        }
+    }
+
+    polygons
+}
+
+// The offset to the left side of the segment from `a` to `b`
+fn offset(a: Point2, b: Point2, hw: f32) -> Point2 {
+    let d = sub(b, a);
+    let len = norm(d);
+    pt2(-d.y / len * hw, d.x / len * hw)
+}
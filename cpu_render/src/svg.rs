@@ -0,0 +1,568 @@
+use nannou::color::LinSrgba;
+use nannou::geom::Point2;
+use nannou::lyon::path::PathEvent;
+
+use nannou::App;
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::draw::{Draw, Transform};
+use crate::input::Input;
+use crate::primitive::{Primitive, Shape};
+use crate::raster::to_srgb_u8;
+use crate::render::fan;
+
+/// Writes a `Draw` as an SVG document, which can be edited with a vector
+/// graphics editor or printed at any size. The coordinates are the same as
+/// `Renderer`'s.
+///
+/// SVG can't interpolate colours between vertices, so the triangles of
+/// meshes and of polygons with per-vertex colours get the average colour of
+/// their vertices.
+pub struct Svg {
+    width: u32,
+    height: u32,
+}
+
+impl Svg {
+    /// `width` and `height` are in points, like the size of a window.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    pub fn render(&self, draw: &Draw) -> String {
+        let (w, h) = (self.width as f32, self.height as f32);
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        )
+        .unwrap();
+        // Flip the y axis and move the origin to the center
+        writeln!(
+            svg,
+            r#"<g transform="matrix(1 0 0 -1 {} {})">"#,
+            num(w / 2.0),
+            num(h / 2.0)
+        )
+        .unwrap();
+
+        for command in draw.commands().iter() {
+            write_primitive(&mut svg, &command.primitive, &command.transform, w, h);
+        }
+
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, draw: &Draw, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        std::fs::write(path, self.render(draw))
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Draws a scene for the current state of the window and saves it as
+/// `file_name` in the sketch's directory, e.g. when a key is pressed:
+///
+/// ```no_run
+/// # use nannou::prelude::*;
+/// # use cpu_render::Input;
+/// # fn scene(draw: &cpu_render::Draw, input: &Input) {}
+/// fn event(app: &App, _model: &mut (), event: Event) {
+///     if let Event::WindowEvent {
+///         simple: Some(KeyPressed(Key::V)),
+///         ..
+///     } = event
+///     {
+///         let file_name = format!("sketch-{}.svg", app.elapsed_frames());
+///         cpu_render::save_svg(app, &file_name, scene);
+///     }
+/// }
+/// ```
+///
+/// Errors are printed rather than returned, as there's nothing else to do
+/// about them in an event handler.
+pub fn save_svg<F>(app: &App, file_name: &str, scene: F)
+where
+    F: FnOnce(&Draw, &Input),
+{
+    let input = Input::from_app(app);
+    let draw = Draw::new();
+    scene(&draw, &input);
+
+    let path = app
+        .project_path()
+        .expect("failed to locate `project_path`")
+        .join(file_name);
+    match input.svg().save(&draw, &path) {
+        Ok(()) => println!("Saved {}", path.display()),
+        Err(e) => eprintln!("Failed to save the SVG: {}", e),
+    }
+}
+
+fn write_primitive(svg: &mut String, primitive: &Primitive, transform: &Transform, w: f32, h: f32) {
+    match primitive {
+        // The background ignores the transform, as it covers the whole image
+        Primitive::Background(background) => {
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+                num(-w / 2.0),
+                num(-h / 2.0),
+                num(w),
+                num(h),
+                paint("fill", Some(background.color)),
+            )
+            .unwrap();
+        }
+        Primitive::Rect(rect) => {
+            let shape = &rect.shape;
+            let (w, h) = (shape.wh.x, shape.wh.y);
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}"{}{}/>"#,
+                num(-w / 2.0),
+                num(-h / 2.0),
+                num(w),
+                num(h),
+                shape_paint(shape),
+                transform_attr(&shape_transform(transform, shape)),
+            )
+            .unwrap();
+        }
+        // The resolution is ignored as SVG has true ellipses.
+        Primitive::Ellipse(ellipse) => {
+            let shape = &ellipse.shape;
+            writeln!(
+                svg,
+                r#"<ellipse rx="{}" ry="{}"{}{}/>"#,
+                num(shape.wh.x / 2.0),
+                num(shape.wh.y / 2.0),
+                shape_paint(shape),
+                transform_attr(&shape_transform(transform, shape)),
+            )
+            .unwrap();
+        }
+        Primitive::Line(line) => {
+            writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"{}{}/>"#,
+                num(line.start.x),
+                num(line.start.y),
+                num(line.end.x),
+                num(line.end.y),
+                stroke_paint(line.color, line.weight),
+                transform_attr(transform),
+            )
+            .unwrap();
+        }
+        Primitive::Polyline(polyline) => {
+            writeln!(
+                svg,
+                r#"<polyline points="{}" fill="none"{}{}/>"#,
+                points(&polyline.points),
+                stroke_paint(polyline.color, polyline.weight),
+                transform_attr(transform),
+            )
+            .unwrap();
+        }
+        Primitive::Polygon(polygon) => match &polygon.colors {
+            Some(colors) => {
+                let triangles = fan(&polygon.points, colors, &Transform::identity());
+                write_triangles(svg, &triangles, transform);
+            }
+            None => {
+                writeln!(
+                    svg,
+                    r#"<polygon points="{}" fill-rule="evenodd"{}{}/>"#,
+                    points(&polygon.points),
+                    paint("fill", Some(polygon.color)),
+                    transform_attr(transform),
+                )
+                .unwrap();
+            }
+        },
+        Primitive::PathFill(path) => {
+            writeln!(
+                svg,
+                r#"<path d="{}" fill-rule="evenodd"{}{}/>"#,
+                path_data(&path.events),
+                paint("fill", Some(path.color)),
+                transform_attr(transform),
+            )
+            .unwrap();
+        }
+        Primitive::PathStroke(path) => {
+            writeln!(
+                svg,
+                r#"<path d="{}" fill="none"{}{}/>"#,
+                path_data(&path.events),
+                stroke_paint(path.color, path.weight),
+                transform_attr(transform),
+            )
+            .unwrap();
+        }
+        Primitive::Mesh(mesh) => {
+            let vertex = |i: usize| {
+                let color = mesh.colors.as_ref().map_or(mesh.color, |colors| colors[i]);
+                (mesh.points[i], color)
+            };
+            let triangles: Vec<_> = mesh
+                .indices
+                .chunks_exact(3)
+                .filter(|tri| tri.iter().all(|&i| i < mesh.points.len()))
+                .map(|tri| [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])])
+                .collect();
+            write_triangles(svg, &triangles, transform);
+        }
+    }
+}
+
+// The triangles are grouped so that they can be selected together in an
+// editor.
+fn write_triangles(svg: &mut String, triangles: &[[(Point2, LinSrgba); 3]], transform: &Transform) {
+    writeln!(svg, "<g{}>", transform_attr(transform)).unwrap();
+    for tri in triangles {
+        let sum = tri.iter().fold([0.0; 4], |mut sum, (_, c)| {
+            let (r, g, b, a) = c.into_components();
+            for (s, v) in sum.iter_mut().zip([r, g, b, a].iter()) {
+                *s += v;
+            }
+            sum
+        });
+        let color = LinSrgba::new(sum[0] / 3.0, sum[1] / 3.0, sum[2] / 3.0, sum[3] / 3.0);
+        let corners: Vec<_> = tri.iter().map(|&(p, _)| p).collect();
+        writeln!(
+            svg,
+            r#"<polygon points="{}"{}/>"#,
+            points(&corners),
+            paint("fill", Some(color)),
+        )
+        .unwrap();
+    }
+    svg.push_str("</g>\n");
+}
+
+fn shape_transform(transform: &Transform, shape: &Shape) -> Transform {
+    transform
+        .then(&Transform::translation(shape.xy.x, shape.xy.y))
+        .then(&Transform::rotation(shape.rotation))
+}
+
+fn shape_paint(shape: &Shape) -> String {
+    let mut attrs = paint("fill", shape.fill);
+    if let Some(color) = shape.stroke {
+        attrs.push_str(&stroke_paint(color, shape.stroke_weight));
+    }
+    attrs
+}
+
+fn stroke_paint(color: LinSrgba, weight: f32) -> String {
+    format!(
+        r#"{} stroke-width="{}""#,
+        paint("stroke", Some(color)),
+        num(weight)
+    )
+}
+
+// e.g. ` fill="#ff0000" fill-opacity="0.5"`
+fn paint(name: &str, color: Option<LinSrgba>) -> String {
+    let color = match color {
+        Some(color) => color,
+        None => return format!(r#" {}="none""#, name),
+    };
+
+    let (r, g, b, a) = color.into_components();
+    let mut attr = format!(
+        r##" {}="#{:02x}{:02x}{:02x}""##,
+        name,
+        to_srgb_u8(r),
+        to_srgb_u8(g),
+        to_srgb_u8(b)
+    );
+    if a < 1.0 {
+        write!(attr, r#" {}-opacity="{}""#, name, num(a.max(0.0))).unwrap();
+    }
+    attr
+}
+
+fn transform_attr(t: &Transform) -> String {
+    if *t == Transform::identity() {
+        return String::new();
+    }
+    format!(
+        r#" transform="matrix({} {} {} {} {} {})""#,
+        num(t.a),
+        num(t.b),
+        num(t.c),
+        num(t.d),
+        num(t.e),
+        num(t.f)
+    )
+}
+
+fn points(points: &[Point2]) -> String {
+    let points: Vec<_> = points
+        .iter()
+        .map(|p| format!("{},{}", num(p.x), num(p.y)))
+        .collect();
+    points.join(" ")
+}
+
+fn path_data(events: &[PathEvent]) -> String {
+    let mut d = String::new();
+    for event in events {
+        match *event {
+            PathEvent::Begin { at } => write!(d, "M{} {} ", num(at.x), num(at.y)),
+            PathEvent::Line { to, .. } => write!(d, "L{} {} ", num(to.x), num(to.y)),
+            PathEvent::Quadratic { ctrl, to, .. } => write!(
+                d,
+                "Q{} {} {} {} ",
+                num(ctrl.x),
+                num(ctrl.y),
+                num(to.x),
+                num(to.y)
+            ),
+            PathEvent::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => write!(
+                d,
+                "C{} {} {} {} {} {} ",
+                num(ctrl1.x),
+                num(ctrl1.y),
+                num(ctrl2.x),
+                num(ctrl2.y),
+                num(to.x),
+                num(to.y)
+            ),
+            PathEvent::End { close: true, .. } => write!(d, "Z "),
+            PathEvent::End { close: false, .. } => Ok(()),
+        }
+        .unwrap();
+    }
+    d.trim_end().to_string()
+}
+
+// Up to 3 decimal places, which is far below a pixel, without the trailing
+// zeros
+fn num(v: f32) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" | "" => "0".to_string(),
+        s => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::geom::pt2;
+
+    fn red() -> LinSrgba {
+        LinSrgba::new(1.0, 0.0, 0.0, 1.0)
+    }
+
+    fn blue() -> LinSrgba {
+        LinSrgba::new(0.0, 0.0, 1.0, 0.5)
+    }
+
+    // The elements drawn inside the flipping group, one per line
+    fn elements(draw: &Draw) -> Vec<String> {
+        let svg = Svg::new(200, 100).render(draw);
+        let lines: Vec<_> = svg.lines().map(str::to_string).collect();
+        lines[2..lines.len() - 2].to_vec()
+    }
+
+    fn assert_element(element: &str, name: &str, attrs: &[&str]) {
+        assert!(
+            element.starts_with(&format!("<{} ", name)),
+            "expected <{}>: {}",
+            name,
+            element
+        );
+        for attr in attrs {
+            assert!(element.contains(attr), "expected {}: {}", attr, element);
+        }
+    }
+
+    #[test]
+    fn document() {
+        let svg = Svg::new(200, 100).render(&Draw::new());
+        assert_eq!(
+            svg,
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 200 100">"#,
+                "\n",
+                r#"<g transform="matrix(1 0 0 -1 100 50)">"#,
+                "\n</g>\n</svg>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn shapes() {
+        let draw = Draw::new();
+        draw.background().color(red());
+        draw.rect()
+            .x_y(10.0, 20.0)
+            .w_h(30.0, 40.0)
+            .color(blue())
+            .stroke(red())
+            .stroke_weight(2.0);
+        draw.ellipse().w_h(20.0, 10.0).no_fill().stroke(red());
+
+        let elements = elements(&draw);
+        assert_eq!(elements.len(), 3);
+        assert_element(
+            &elements[0],
+            "rect",
+            &[
+                r#"x="-100" y="-50" width="200" height="100""#,
+                r##"fill="#ff0000""##,
+            ],
+        );
+        assert_element(
+            &elements[1],
+            "rect",
+            &[
+                r#"x="-15" y="-20" width="30" height="40""#,
+                r##"fill="#0000ff" fill-opacity="0.5""##,
+                r##"stroke="#ff0000" stroke-width="2""##,
+                r#"transform="matrix(1 0 0 1 10 20)""#,
+            ],
+        );
+        assert_element(
+            &elements[2],
+            "ellipse",
+            &[
+                r#"rx="10" ry="5""#,
+                r#"fill="none""#,
+                r##"stroke="#ff0000""##,
+            ],
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let draw = Draw::new();
+        draw.line()
+            .points(pt2(0.0, 0.0), pt2(10.0, 5.0))
+            .weight(3.0)
+            .color(red());
+        draw.x_y(5.0, 0.0)
+            .polyline()
+            .points(vec![pt2(0.0, 0.0), pt2(1.0, 2.0), pt2(3.0, 0.5)])
+            .weight(1.5)
+            .color(blue());
+
+        let elements = elements(&draw);
+        assert_eq!(elements.len(), 2);
+        assert_element(
+            &elements[0],
+            "line",
+            &[
+                r#"x1="0" y1="0" x2="10" y2="5""#,
+                r##"stroke="#ff0000" stroke-width="3""##,
+            ],
+        );
+        assert_element(
+            &elements[1],
+            "polyline",
+            &[
+                r#"points="0,0 1,2 3,0.5""#,
+                r#"fill="none""#,
+                r##"stroke="#0000ff" stroke-opacity="0.5" stroke-width="1.5""##,
+                r#"transform="matrix(1 0 0 1 5 0)""#,
+            ],
+        );
+    }
+
+    #[test]
+    fn paths() {
+        let square = vec![
+            pt2(0.0, 0.0),
+            pt2(10.0, 0.0),
+            pt2(10.0, 10.0),
+            pt2(0.0, 10.0),
+        ];
+        let draw = Draw::new();
+        draw.polygon().points(square.clone()).color(red());
+        draw.path()
+            .fill()
+            .points_closed(square.clone())
+            .color(red());
+        draw.path()
+            .stroke()
+            .weight(2.0)
+            .points(square)
+            .color(blue());
+
+        let elements = elements(&draw);
+        assert_eq!(elements.len(), 3);
+        assert_element(
+            &elements[0],
+            "polygon",
+            &[
+                r#"points="0,0 10,0 10,10 0,10""#,
+                r#"fill-rule="evenodd""#,
+                r##"fill="#ff0000""##,
+            ],
+        );
+        assert_element(
+            &elements[1],
+            "path",
+            &[
+                r#"d="M0 0 L10 0 L10 10 L0 10 Z""#,
+                r#"fill-rule="evenodd""#,
+                r##"fill="#ff0000""##,
+            ],
+        );
+        assert_element(
+            &elements[2],
+            "path",
+            &[
+                r#"d="M0 0 L10 0 L10 10 L0 10""#,
+                r#"fill="none""#,
+                r##"stroke="#0000ff" stroke-opacity="0.5" stroke-width="2""##,
+            ],
+        );
+    }
+
+    // Only meshes, which have no outline, are written as triangles
+    #[test]
+    fn mesh() {
+        let draw = Draw::new();
+        draw.mesh()
+            .indexed(
+                vec![
+                    pt2(0.0, 0.0),
+                    pt2(10.0, 0.0),
+                    pt2(10.0, 10.0),
+                    pt2(0.0, 10.0),
+                ],
+                vec![0, 1, 2, 0, 2, 3],
+            )
+            .color(red());
+
+        let elements = elements(&draw);
+        assert_eq!(elements.len(), 4);
+        assert_eq!(elements[0], "<g>");
+        assert_element(&elements[1], "polygon", &[r#"points="0,0 10,0 10,10""#]);
+        assert_element(&elements[2], "polygon", &[r#"points="0,0 10,10 0,10""#]);
+        assert_eq!(elements[3], "</g>");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(0.5), "0.5");
+        assert_eq!(num(1.23456), "1.235");
+        assert_eq!(num(-0.0001), "0");
+    }
+}
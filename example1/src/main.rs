@@ -1,7 +1,8 @@
+use cpu_render::Input;
 use nannou::prelude::*;
 
 fn main() {
-    nannou::app(model).simple_window(view).run();
+    nannou::app(model).event(event).simple_window(view).run();
 }
 
 struct Model {}
@@ -10,10 +11,9 @@ fn model(_app: &App) -> Model {
     Model {}
 }
 
-fn view(app: &App, _model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn scene(draw: &cpu_render::Draw, input: &Input) {
     let pad = 25.0;
-    let win = app.window_rect();
+    let win = input.window_rect;
     let win_p = win.pad(pad);
 
     let square = Rect::from_w_h(100.0, 100.0).top_left_of(win_p);
@@ -22,5 +22,35 @@ fn view(app: &App, _model: &Model, frame: Frame) {
 
     let circle = square.below(square).shift_y(-pad);
     draw.ellipse().xy(circle.xy()).wh(circle.wh()).color(SALMON);
+}
+
+// Press V to save the current frame as an SVG file
+fn event(app: &App, _model: &mut Model, event: Event) {
+    if let Event::WindowEvent {
+        simple: Some(KeyPressed(Key::V)),
+        ..
+    } = event
+    {
+        let file_name = format!("example1-{}.svg", app.elapsed_frames());
+        cpu_render::save_svg(app, &file_name, scene);
+    }
+}
+
+fn view(app: &App, _model: &Model, frame: Frame) {
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, &Input::from_app(app));
+
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden() {
+        cpu_render::assert_scene("example1", &Input::new(1024, 768), scene);
+    }
+}
@@ -1,12 +1,11 @@
+use cpu_render::Input;
 use nannou::prelude::*;
 
 fn main() {
     nannou::sketch(view).run();
 }
 
-fn view(app: &App, frame: Frame) {
-    let draw = app.draw();
-
+fn scene(draw: &cpu_render::Draw, _input: &Input) {
     draw.background().color(PLUM);
 
     let radius = 150.0;
@@ -19,6 +18,23 @@ fn view(app: &App, frame: Frame) {
     });
 
     draw.polygon().points_colored(points);
+}
+
+fn view(app: &App, frame: Frame) {
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, &Input::from_app(app));
 
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden() {
+        cpu_render::assert_scene("example2", &Input::new(1024, 768), scene);
+    }
+}
@@ -1,3 +1,4 @@
+use cpu_render::Input;
 use nannou::prelude::*;
 
 struct Model {}
@@ -12,20 +13,35 @@ fn model(_app: &App) -> Model {
 
 fn event(_app: &App, _model: &mut Model, _event: Event) {}
 
-fn view(app: &App, _model: &Model, frame: Frame) {
-    let draw = app.draw();
-
+fn scene(draw: &cpu_render::Draw, input: &Input) {
     draw.background().color(PLUM);
 
-    let sine = app.time.sin();
-    let slowsine = (app.time / 2.0).sin();
-    let boundary = app.window_rect();
+    let sine = input.time.sin();
+    let slowsine = (input.time / 2.0).sin();
+    let boundary = input.window_rect;
 
     let x = map_range(sine, -1.0, 1.0, boundary.left(), boundary.right());
     let y = map_range(slowsine, -1.0, 1.0, boundary.bottom(), boundary.top());
 
     // Draw a blue ellipse with a radius of 10 at the (x,y) coordinates of (0.0, 0.0)
     draw.ellipse().color(STEELBLUE).x_y(x, y);
+}
+
+fn view(app: &App, _model: &Model, frame: Frame) {
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, &Input::from_app(app));
 
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden() {
+        cpu_render::assert_scene("example3", &Input::new(1024, 768).time(1.5), scene);
+    }
+}
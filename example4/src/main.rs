@@ -1,16 +1,14 @@
+use cpu_render::Input;
 use nannou::prelude::*;
 
 fn main() {
     nannou::sketch(view).run();
 }
 
-fn view(app: &App, frame: Frame) {
-    // Prepare to draw.
-    let draw = app.draw();
+fn scene(draw: &cpu_render::Draw, input: &Input) {
+    let win = input.window_rect;
 
-    let win = app.window_rect();
-
-    let color_select = map_range(app.mouse.y, win.top(), win.bottom(), 0.0, 5.0) as i32;
+    let color_select = map_range(input.mouse.y, win.top(), win.bottom(), 0.0, 5.0) as i32;
 
     let bg_color = match color_select {
         0 => RED,
@@ -23,12 +21,34 @@ fn view(app: &App, frame: Frame) {
 
     draw.background().color(bg_color);
 
-    if app.mouse.x < 0.0 {
+    if input.mouse.x < 0.0 {
         draw.ellipse().color(STEELBLUE);
     } else {
         draw.ellipse().color(SEAGREEN);
     }
+}
+
+fn view(app: &App, frame: Frame) {
+    // Prepare to draw.
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, &Input::from_app(app));
 
     // Draw to the window frame.
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden() {
+        cpu_render::assert_scene(
+            "example4",
+            &Input::new(1024, 768).mouse(-100.0, 200.0),
+            scene,
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use nannou::geom::Rect;
+
+use rapier2d::data::arena::Index;
+use rapier2d::dynamics::{JointSet, RigidBodyBuilder, RigidBodySet};
+use rapier2d::geometry::{ColliderBuilder, ColliderSet};
+use rapier2d::na::Point2;
+
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+// The walls are thick enough so that fast bodies don't tunnel through them
+const THICKNESS: f32 = 2.0;
+
+/// The static walls, floor and ceiling around the visible area.
+#[derive(Serialize, Deserialize)]
+pub struct Bounds {
+    pub floor: bool,
+    pub ceiling: bool,
+    pub walls: bool,
+    friction: f32,
+    body: Option<Index>,
+}
+
+impl Bounds {
+    pub fn new() -> Self {
+        Self {
+            floor: true,
+            ceiling: false,
+            walls: true,
+            friction: 0.8,
+            body: None,
+        }
+    }
+
+    pub fn floor(mut self, floor: bool) -> Self {
+        self.floor = floor;
+        self
+    }
+
+    pub fn ceiling(mut self, ceiling: bool) -> Self {
+        self.ceiling = ceiling;
+        self
+    }
+
+    pub fn walls(mut self, walls: bool) -> Self {
+        self.walls = walls;
+        self
+    }
+
+    /// (Re)creates the colliders along the edges of `rect`, which is in
+    /// meters. The previous colliders, if any, are removed; this is called
+    /// every time the window is resized.
+    pub fn build(
+        &mut self,
+        rect: Rect,
+        bodies: &mut RigidBodySet,
+        colliders: &mut ColliderSet,
+        joints: &mut JointSet,
+    ) {
+        if let Some(body) = self.body.take() {
+            bodies.remove(body, colliders, joints);
+        }
+
+        let body = bodies.insert(RigidBodyBuilder::new_static().build());
+
+        let (hw, hh, ht) = (rect.w() / 2.0, rect.h() / 2.0, THICKNESS / 2.0);
+        let mut sides = vec![];
+        if self.floor {
+            sides.push((rect.x(), rect.bottom() - ht, hw + THICKNESS, ht));
+        }
+        if self.ceiling {
+            sides.push((rect.x(), rect.top() + ht, hw + THICKNESS, ht));
+        }
+        if self.walls {
+            sides.push((rect.left() - ht, rect.y(), ht, hh + THICKNESS));
+            sides.push((rect.right() + ht, rect.y(), ht, hh + THICKNESS));
+        }
+
+        for (x, y, hx, hy) in sides {
+            let coll = ColliderBuilder::cuboid(hx, hy)
+                .translation(x, y)
+                .friction(self.friction)
+                .build();
+            colliders.insert(coll, body, bodies);
+        }
+
+        self.body = Some(body);
+    }
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adds a static polyline as an obstacle.
+pub fn add_obstacle(
+    points: &[Point2<f32>],
+    bodies: &mut RigidBodySet,
+    colliders: &mut ColliderSet,
+) {
+    let body = bodies.insert(RigidBodyBuilder::new_static().build());
+    for seg in points.windows(2) {
+        let coll = ColliderBuilder::segment(seg[0], seg[1])
+            .friction(0.8)
+            .build();
+        colliders.insert(coll, body, bodies);
+    }
+}
+
+/// Loads polylines from a text file. Each line is a polyline whose points
+/// are written as `x,y` in meters and separated by whitespaces, e.g.:
+///
+/// ```text
+/// # a slope
+/// -10,0 -2,-4
+/// ```
+pub fn load_obstacles(path: &Path) -> Result<Vec<Vec<Point2<f32>>>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+    let mut polylines = vec![];
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let points = line
+            .split_whitespace()
+            .map(|xy| {
+                let mut iter = xy.split(',').map(|v| v.trim().parse::<f32>());
+                match (iter.next(), iter.next(), iter.next()) {
+                    (Some(Ok(x)), Some(Ok(y)), None) => Ok(Point2::new(x, y)),
+                    _ => Err(format!(
+                        "{}:{}: invalid point \"{}\"",
+                        path.display(),
+                        line_no + 1,
+                        xy
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        polylines.push(points);
+    }
+
+    Ok(polylines)
+}
@@ -0,0 +1,186 @@
+use nannou::geom::{pt2, Point2 as ScreenPoint, Rect};
+
+use rapier2d::na::{Point2, Vector2};
+
+// The range of the zoom, which keeps the scale from reaching zero
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 100.0;
+
+/// A 2D camera that maps the physics world (in meters) to the window (in
+/// pixels). All the conversions between them should go through this so that
+/// the text size and the window size can be changed without retuning.
+pub struct Camera {
+    /// The scale when the zoom is 1.0
+    pub pixels_per_meter: f32,
+    pub zoom: f32,
+    /// The point of the world at the center of the window
+    pub center: Point2<f32>,
+    // The last mouse position while dragging
+    drag_from: Option<ScreenPoint>,
+}
+
+impl Camera {
+    pub fn new(pixels_per_meter: f32) -> Self {
+        Self {
+            pixels_per_meter,
+            zoom: 1.0,
+            center: Point2::origin(),
+            drag_from: None,
+        }
+    }
+
+    /// Pixels per meter, including the zoom
+    pub fn scale(&self) -> f32 {
+        self.pixels_per_meter * self.zoom
+    }
+
+    pub fn to_screen(&self, p: &Point2<f32>) -> ScreenPoint {
+        let v = (p - self.center) * self.scale();
+        pt2(v.x, v.y)
+    }
+
+    pub fn to_world(&self, p: ScreenPoint) -> Point2<f32> {
+        self.center + Vector2::new(p.x, p.y) / self.scale()
+    }
+
+    /// The area of the world visible in `win`
+    pub fn world_rect(&self, win: Rect) -> Rect {
+        let bottom_left = self.to_world(win.bottom_left());
+        let top_right = self.to_world(win.top_right());
+        Rect::from_corners(
+            pt2(bottom_left.x, bottom_left.y),
+            pt2(top_right.x, top_right.y),
+        )
+    }
+
+    /// Zooms by `factor` while keeping the world point under `screen_pos`
+    /// (e.g. the mouse) at the same place.
+    pub fn zoom_at(&mut self, factor: f32, screen_pos: ScreenPoint) {
+        let anchor = self.to_world(screen_pos);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center = anchor - Vector2::new(screen_pos.x, screen_pos.y) / self.scale();
+    }
+
+    /// Adjusts the zoom and the center so that the world area between `min`
+    /// and `max` fits in `win` with `padding` pixels around it. The zoom is
+    /// clamped as in `zoom_at()`, e.g. if the padding leaves no room.
+    pub fn fit(&mut self, min: Point2<f32>, max: Point2<f32>, win: Rect, padding: f32) {
+        let size = max - min;
+        if size.x <= 0.0 && size.y <= 0.0 {
+            return;
+        }
+
+        let w = (win.w() - padding * 2.0).max(0.0);
+        let h = (win.h() - padding * 2.0).max(0.0);
+        let scale = (w / size.x).min(h / size.y);
+        self.zoom = (scale / self.pixels_per_meter).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center = min + size / 2.0;
+    }
+
+    pub fn start_drag(&mut self, mouse: ScreenPoint) {
+        self.drag_from = Some(mouse);
+    }
+
+    pub fn drag(&mut self, mouse: ScreenPoint) {
+        if let Some(from) = self.drag_from {
+            let d = mouse - from;
+            self.center -= Vector2::new(d.x, d.y) / self.scale();
+            self.drag_from = Some(mouse);
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_from = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Point2<f32>, b: Point2<f32>) {
+        assert!((a - b).norm() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn to_world_inverts_to_screen() {
+        let mut camera = Camera::new(100.0);
+        camera.zoom = 2.0;
+        camera.center = Point2::new(1.0, 2.0);
+
+        let p = Point2::new(3.0, -1.0);
+        let screen = camera.to_screen(&p);
+        assert_eq!(screen, pt2(400.0, -600.0));
+        assert_near(camera.to_world(screen), p);
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_mouse() {
+        let mut camera = Camera::new(100.0);
+        let mouse = pt2(120.0, -40.0);
+        let before = camera.to_world(mouse);
+
+        camera.zoom_at(1.5, mouse);
+        assert_eq!(camera.zoom, 1.5);
+        assert_near(camera.to_world(mouse), before);
+
+        // clamped
+        camera.zoom_at(1e6, mouse);
+        assert_eq!(camera.zoom, 100.0);
+        assert_near(camera.to_world(mouse), before);
+    }
+
+    #[test]
+    fn fit_centers_and_scales_the_area() {
+        let mut camera = Camera::new(100.0);
+        let win = Rect::from_w_h(1000.0, 500.0);
+        camera.fit(Point2::new(0.0, 0.0), Point2::new(4.0, 2.0), win, 50.0);
+
+        // the height is the tighter one: 400 pixels for 2 meters
+        assert_eq!(camera.scale(), 200.0);
+        assert_near(camera.center, Point2::new(2.0, 1.0));
+        let visible = camera.world_rect(win);
+        assert!(visible.left() <= 0.0 && visible.right() >= 4.0);
+        assert!(visible.bottom() <= 0.0 && visible.top() >= 2.0);
+    }
+
+    #[test]
+    fn fit_ignores_an_empty_area() {
+        let mut camera = Camera::new(100.0);
+        let p = Point2::new(1.0, 1.0);
+        camera.fit(p, p, Rect::from_w_h(1000.0, 500.0), 50.0);
+        assert_eq!(camera.zoom, 1.0);
+        assert_eq!(camera.center, Point2::origin());
+    }
+
+    #[test]
+    fn fit_clamps_the_zoom() {
+        let mut camera = Camera::new(100.0);
+        let win = Rect::from_w_h(1000.0, 500.0);
+
+        // the padding is larger than the window
+        camera.fit(Point2::new(0.0, 0.0), Point2::new(4.0, 2.0), win, 300.0);
+        assert_eq!(camera.zoom, MIN_ZOOM);
+        assert_near(camera.center, Point2::new(2.0, 1.0));
+
+        // a tiny area
+        camera.fit(Point2::new(0.0, 0.0), Point2::new(1e-4, 1e-4), win, 50.0);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+    }
+
+    #[test]
+    fn drag_moves_the_world_with_the_mouse() {
+        let mut camera = Camera::new(100.0);
+        let grabbed = camera.to_world(pt2(10.0, 10.0));
+
+        camera.start_drag(pt2(10.0, 10.0));
+        camera.drag(pt2(60.0, -20.0));
+        camera.drag(pt2(110.0, 30.0));
+        assert_near(camera.to_world(pt2(110.0, 30.0)), grabbed);
+
+        camera.end_drag();
+        let center = camera.center;
+        camera.drag(pt2(0.0, 0.0));
+        assert_eq!(camera.center, center);
+    }
+}
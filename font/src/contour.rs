@@ -0,0 +1,301 @@
+use rapier2d::na::Point2;
+
+use lyon::math::{point, Point};
+use lyon::path::Path;
+use lyon::tessellation::geometry_builder::{BuffersBuilder, VertexBuffers};
+use lyon::tessellation::{FillAttributes, FillOptions, FillRule, FillTessellator};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ContourKind {
+    /// The outline of a glyph
+    Outer,
+    /// The counter of a glyph, e.g. the inside of "o"
+    Hole,
+}
+
+/// How to resample a contour.
+#[derive(Clone, Copy, Debug)]
+pub enum Sampling {
+    /// Place points at this arc-length interval. The interval is adjusted
+    /// slightly so that the points are distributed evenly over the contour.
+    Spacing(f32),
+    /// Place exactly this number of points.
+    Count(usize),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Contour {
+    pub points: Vec<Point2<f32>>,
+    pub closed: bool,
+    pub path_id: u32,
+    pub glyph_id: u32,
+    pub kind: ContourKind,
+}
+
+impl Contour {
+    /// The area by the shoelace formula; positive if the points are in
+    /// counter-clockwise order.
+    pub fn signed_area(&self) -> f32 {
+        signed_area(&self.points)
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (Point2<f32>, Point2<f32>)> + '_ {
+        let n_segments = if self.closed {
+            self.points.len()
+        } else {
+            self.points.len().saturating_sub(1)
+        };
+        let n = self.points.len();
+        (0..n_segments).map(move |i| (self.points[i], self.points[(i + 1) % n]))
+    }
+
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(from, to)| (to - from).norm()).sum()
+    }
+
+    /// Returns a new contour whose points are placed at even arc-length
+    /// intervals along this contour, so that the number of points no longer
+    /// depends on how the curves were flattened.
+    ///
+    /// Panics if a `Sampling::Spacing` is not positive.
+    pub fn resampled(&self, sampling: Sampling) -> Contour {
+        let length = self.length();
+        // a closed contour needs at least a triangle, an open one a line
+        let min_count = if self.closed { 3 } else { 2 };
+
+        let count = match sampling {
+            Sampling::Spacing(spacing) => {
+                assert!(spacing > 0.0, "invalid spacing: {}", spacing);
+                (length / spacing).round() as usize
+            }
+            Sampling::Count(count) => count,
+        }
+        .max(min_count);
+
+        if self.points.len() < 2 || length == 0.0 {
+            return self.clone();
+        }
+
+        // an open contour has points on both ends, so the number of
+        // intervals is one less than the number of points
+        let step = if self.closed {
+            length / count as f32
+        } else {
+            length / (count - 1) as f32
+        };
+
+        let mut points = Vec::with_capacity(count);
+        let mut segments = self.segments();
+        let (mut from, mut to) = segments.next().unwrap();
+        // the arc-length at `from`
+        let mut travelled = 0.0;
+
+        for i in 0..count {
+            let target = (i as f32 * step).min(length);
+            let mut seg_len = (to - from).norm();
+
+            while travelled + seg_len < target {
+                match segments.next() {
+                    Some(seg) => {
+                        travelled += seg_len;
+                        from = seg.0;
+                        to = seg.1;
+                        seg_len = (to - from).norm();
+                    }
+                    // this can happen only by the floating point error
+                    None => break,
+                }
+            }
+
+            let t = if seg_len > 0.0 {
+                ((target - travelled) / seg_len).min(1.0)
+            } else {
+                0.0
+            };
+            points.push(from + (to - from) * t);
+        }
+
+        Contour {
+            points,
+            closed: self.closed,
+            path_id: self.path_id,
+            glyph_id: self.glyph_id,
+            kind: self.kind,
+        }
+    }
+}
+
+pub fn signed_area(points: &[Point2<f32>]) -> f32 {
+    let n = points.len();
+    let mut area = 0.0;
+    for i in 0..n {
+        let p = points[i];
+        let p_next = points[(i + 1) % n];
+        area += p.x * p_next.y - p_next.x * p.y;
+    }
+    area / 2.0
+}
+
+/// Decides whether each contour is an outline or a hole.
+///
+/// TrueType and CFF fonts use the opposite winding directions, so we cannot
+/// rely on the sign alone. Instead, the largest contour of each glyph is
+/// regarded as an outline, and the contours wound in the opposite direction
+/// to it are regarded as holes.
+pub fn classify(contours: &mut [Contour]) {
+    let mut glyph_ids: Vec<u32> = contours.iter().map(|c| c.glyph_id).collect();
+    glyph_ids.dedup();
+
+    for glyph_id in glyph_ids {
+        let outer_sign = contours
+            .iter()
+            .filter(|c| c.glyph_id == glyph_id)
+            .map(|c| c.signed_area())
+            .fold(0.0_f32, |acc, a| if a.abs() > acc.abs() { a } else { acc })
+            .signum();
+
+        for c in contours.iter_mut().filter(|c| c.glyph_id == glyph_id) {
+            c.kind = if c.signed_area().signum() == outer_sign {
+                ContourKind::Outer
+            } else {
+                ContourKind::Hole
+            };
+        }
+    }
+}
+
+/// Triangulates the inside of the closed contours (e.g. the ones of a glyph)
+/// by lyon's fill tessellator. Holes are respected.
+pub fn triangulate(contours: &[Contour], tolerance: f32) -> VertexBuffers<Point2<f32>, u32> {
+    let mut builder = Path::builder();
+    for c in contours.iter().filter(|c| c.closed && c.points.len() >= 3) {
+        builder.move_to(point(c.points[0].x, c.points[0].y));
+        for p in &c.points[1..] {
+            builder.line_to(point(p.x, p.y));
+        }
+        builder.close();
+    }
+    let path = builder.build();
+
+    let mut geometry: VertexBuffers<Point2<f32>, u32> = VertexBuffers::new();
+    let mut tessellator = FillTessellator::new();
+    // holes are wound in the opposite direction, but even-odd doesn't
+    // care which direction the outlines are wound in
+    let result = tessellator.tessellate_path(
+        &path,
+        &FillOptions::tolerance(tolerance).with_fill_rule(FillRule::EvenOdd),
+        &mut BuffersBuilder::new(&mut geometry, |p: Point, _: FillAttributes| {
+            Point2::new(p.x, p.y)
+        }),
+    );
+    if result.is_err() {
+        return VertexBuffers::new();
+    }
+
+    geometry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(size: f32) -> Contour {
+        Contour {
+            points: vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(size, 0.0),
+                Point2::new(size, size),
+                Point2::new(0.0, size),
+            ],
+            closed: true,
+            path_id: 0,
+            glyph_id: 0,
+            kind: ContourKind::Outer,
+        }
+    }
+
+    fn reversed(mut c: Contour) -> Contour {
+        c.points.reverse();
+        c
+    }
+
+    // A smaller square in the middle of `square(size)`
+    fn inner_square(size: f32) -> Contour {
+        let mut c = square(size / 2.0);
+        for p in c.points.iter_mut() {
+            *p += rapier2d::na::Vector2::new(size / 4.0, size / 4.0);
+        }
+        c
+    }
+
+    #[test]
+    fn classify_finds_holes_by_the_winding_of_the_largest_contour() {
+        // counter-clockwise outlines as in CFF fonts, and clockwise ones as in
+        // TrueType fonts
+        for &clockwise in &[false, true] {
+            let mut outer = square(10.0);
+            let mut inner = reversed(inner_square(10.0));
+            if clockwise {
+                outer = reversed(outer);
+                inner = reversed(inner);
+            }
+            // the hole comes first to make sure it's not decided by the order
+            let mut contours = vec![inner, outer];
+            classify(&mut contours);
+            assert_eq!(contours[0].kind, ContourKind::Hole);
+            assert_eq!(contours[1].kind, ContourKind::Outer);
+        }
+    }
+
+    #[test]
+    fn classify_each_glyph_separately() {
+        // the second glyph is wound the other way round
+        let mut second = reversed(square(1.0));
+        second.glyph_id = 1;
+        let mut contours = vec![square(10.0), second];
+        classify(&mut contours);
+        assert_eq!(contours[0].kind, ContourKind::Outer);
+        assert_eq!(contours[1].kind, ContourKind::Outer);
+    }
+
+    #[test]
+    fn resample_by_spacing() {
+        let c = square(10.0).resampled(Sampling::Spacing(2.5));
+        assert_eq!(c.points.len(), 16);
+        // every point stays on the square and the spacing is even
+        for (from, to) in c.segments() {
+            assert!(((to - from).norm() - 2.5).abs() < 1e-4);
+        }
+        assert!((c.length() - 40.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn resample_by_count_keeps_open_ends() {
+        let mut c = square(10.0);
+        c.closed = false;
+        let r = c.resampled(Sampling::Count(7));
+        assert_eq!(r.points.len(), 7);
+        assert_eq!(r.points[0], c.points[0]);
+        assert!((r.points[6] - c.points[3]).norm() < 1e-4);
+    }
+
+    #[test]
+    fn resample_keeps_a_triangle() {
+        let c = square(1.0).resampled(Sampling::Spacing(100.0));
+        assert_eq!(c.points.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid spacing")]
+    fn resample_rejects_zero_spacing() {
+        square(10.0).resampled(Sampling::Spacing(0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid spacing")]
+    fn resample_rejects_nan_spacing() {
+        square(10.0).resampled(Sampling::Spacing(f32::NAN));
+    }
+}
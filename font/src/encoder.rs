@@ -0,0 +1,442 @@
+use nannou::image::{imageops, RgbaImage};
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    /// The frame rate of the captured frames
+    pub source_fps: f32,
+    /// The frame rate of the animation. Frames are skipped if this is lower
+    /// than `source_fps`.
+    pub fps: f32,
+    /// The size of the animation relative to the captured frames
+    pub scale: f32,
+    /// The number of times to play, or `None` to loop forever
+    pub plays: Option<u16>,
+    /// The speed of the GIF palette quantization, from 1 (best quality) to 30
+    pub gif_speed: i32,
+}
+
+impl AnimationOptions {
+    pub fn new(format: AnimationFormat) -> Self {
+        Self {
+            format,
+            source_fps: 60.0,
+            fps: 30.0,
+            scale: 1.0,
+            plays: None,
+            gif_speed: 10,
+        }
+    }
+
+    pub fn source_fps(mut self, source_fps: f32) -> Self {
+        self.source_fps = source_fps;
+        self
+    }
+
+    pub fn fps(mut self, fps: f32) -> Self {
+        self.fps = fps;
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn plays(mut self, plays: Option<u16>) -> Self {
+        self.plays = plays;
+        self
+    }
+
+    // Keep every n-th captured frame
+    fn frame_step(&self) -> u32 {
+        (self.source_fps / self.fps).round().max(1.0) as u32
+    }
+}
+
+/// Sends a captured frame with its frame number. This can be cloned and
+/// moved to the callback of the texture capturer.
+pub type FrameSender = Sender<(u32, RgbaImage)>;
+
+/// Encodes the captured frames into an animated GIF or APNG on a background
+/// thread, without writing the frames to PNG files first.
+pub struct AnimationEncoder {
+    sender: FrameSender,
+    handle: JoinHandle<Result<(), String>>,
+}
+
+impl AnimationEncoder {
+    pub fn start(path: PathBuf, options: AnimationOptions) -> Self {
+        let (sender, receiver) = channel();
+        let handle = std::thread::spawn(move || encode(&path, options, receiver));
+        Self { sender, handle }
+    }
+
+    pub fn sender(&self) -> FrameSender {
+        self.sender.clone()
+    }
+
+    /// Waits until all the frames are encoded. Note that all the senders
+    /// must be dropped, otherwise this blocks forever.
+    pub fn finish(self) -> Result<(), String> {
+        drop(self.sender);
+        self.handle
+            .join()
+            .map_err(|_| "the encoder thread panicked".to_string())?
+    }
+}
+
+fn encode(
+    path: &Path,
+    options: AnimationOptions,
+    receiver: Receiver<(u32, RgbaImage)>,
+) -> Result<(), String> {
+    let err = |e: Box<dyn std::error::Error>| format!("{}: {}", path.display(), e);
+
+    let mut frames = FrameOrder::new(options.frame_step());
+    let mut writer = None;
+
+    for (n, image) in receiver {
+        for image in frames.push(n, image) {
+            write_frame(&mut writer, path, image, &options).map_err(err)?;
+        }
+    }
+
+    // Some frames might have failed to be captured
+    for image in frames.rest() {
+        write_frame(&mut writer, path, image, &options).map_err(err)?;
+    }
+
+    match writer {
+        Some(writer) => writer.finish(path).map_err(err)?,
+        None => return Ok(()),
+    }
+
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+// A frame that hasn't arrived while this many later frames have is
+// regarded as lost, so that the frames don't pile up waiting for it
+const MAX_PENDING: usize = 16;
+
+/// Puts the frames to keep back in order, as they can arrive out of order
+/// from the texture capturer, which works asynchronously.
+struct FrameOrder<T> {
+    frame_step: u32,
+    // the number of the frame to write next
+    next: u32,
+    pending: BTreeMap<u32, T>,
+}
+
+impl<T> FrameOrder<T> {
+    fn new(frame_step: u32) -> Self {
+        Self {
+            frame_step,
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the frames that are ready to be written, in order.
+    fn push(&mut self, n: u32, frame: T) -> Vec<T> {
+        // the frames to skip, and the ones that arrive after they were
+        // given up on
+        if !n.is_multiple_of(self.frame_step) || n < self.next {
+            return vec![];
+        }
+        self.pending.insert(n, frame);
+
+        if self.pending.len() > MAX_PENDING {
+            self.next = *self.pending.keys().next().unwrap();
+        }
+
+        let mut ready = vec![];
+        while let Some(frame) = self.pending.remove(&self.next) {
+            ready.push(frame);
+            self.next += self.frame_step;
+        }
+        ready
+    }
+
+    /// The frames still waiting for the ones before them, in order.
+    fn rest(self) -> impl Iterator<Item = T> {
+        self.pending.into_values()
+    }
+}
+
+type BoxResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+enum Writer {
+    Gif(gif::Encoder<BufWriter<File>>),
+    // APNG needs the number of frames before writing any frame, so it's
+    // patched in when the frames are done; `u32` is the number written
+    Apng(png::Writer<BufWriter<File>>, u32),
+}
+
+impl Writer {
+    fn new(path: &Path, width: u32, height: u32, options: &AnimationOptions) -> BoxResult<Self> {
+        let w = BufWriter::new(File::create(path)?);
+
+        match options.format {
+            AnimationFormat::Gif => {
+                let mut encoder = gif::Encoder::new(w, width as u16, height as u16, &[])?;
+                encoder.set_repeat(match options.plays {
+                    Some(n) => gif::Repeat::Finite(n),
+                    None => gif::Repeat::Infinite,
+                })?;
+                Ok(Writer::Gif(encoder))
+            }
+            AnimationFormat::Apng => {
+                let mut encoder = png::Encoder::new(w, width, height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                // 0 means infinite
+                encoder.set_animated(u32::MAX, options.plays.unwrap_or(0) as u32)?;
+
+                let mut writer = encoder.write_header()?;
+                writer.set_frame_delay(1, options.fps.round() as u16)?;
+                Ok(Writer::Apng(writer, 0))
+            }
+        }
+    }
+
+    fn write(&mut self, image: RgbaImage, options: &AnimationOptions) -> BoxResult<()> {
+        match self {
+            Writer::Gif(encoder) => {
+                let (width, height) = image.dimensions();
+                let mut pixels = image.into_raw();
+                // each frame gets its own palette optimised by NeuQuant
+                let mut frame = gif::Frame::from_rgba_speed(
+                    width as u16,
+                    height as u16,
+                    &mut pixels,
+                    options.gif_speed,
+                );
+                // the delay is in the unit of 1/100 seconds
+                frame.delay = (100.0 / options.fps).round() as u16;
+                encoder.write_frame(&frame)?;
+            }
+            Writer::Apng(writer, written) => {
+                writer.write_image_data(image.as_raw())?;
+                *written += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, path: &Path) -> BoxResult<()> {
+        match self {
+            // the trailer is written on drop
+            Writer::Gif(_) => {}
+            Writer::Apng(writer, written) => {
+                writer.finish()?;
+                patch_frame_count(path, written)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Rewrites the number of frames in the acTL chunk of an APNG file, which
+// comes before the frames.
+fn patch_frame_count(path: &Path, n_frames: u32) -> BoxResult<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+    // skip the signature
+    let mut offset = 8;
+    loop {
+        let mut header = [0; 8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let chunk_type = &header[4..];
+
+        if chunk_type == b"acTL" {
+            let mut data = [0; 8];
+            file.read_exact(&mut data)?;
+            data[..4].copy_from_slice(&n_frames.to_be_bytes());
+
+            let mut crc = crc32fast::Hasher::new();
+            crc.update(chunk_type);
+            crc.update(&data);
+
+            file.seek(SeekFrom::Start(offset + 8))?;
+            file.write_all(&data)?;
+            file.write_all(&crc.finalize().to_be_bytes())?;
+            return Ok(());
+        }
+        if chunk_type == b"IDAT" || chunk_type == b"IEND" {
+            return Err("no acTL chunk".into());
+        }
+        // the length doesn't include the type and the CRC
+        offset += 8 + length as u64 + 4;
+    }
+}
+
+fn write_frame(
+    writer: &mut Option<Writer>,
+    path: &Path,
+    image: RgbaImage,
+    options: &AnimationOptions,
+) -> BoxResult<()> {
+    let image = resize(image, options.scale);
+    if writer.is_none() {
+        *writer = Some(Writer::new(path, image.width(), image.height(), options)?);
+    }
+    writer.as_mut().unwrap().write(image, options)
+}
+
+fn resize(image: RgbaImage, scale: f32) -> RgbaImage {
+    if (scale - 1.0).abs() < f32::EPSILON {
+        return image;
+    }
+
+    let w = ((image.width() as f32 * scale).round() as u32).max(1);
+    let h = ((image.height() as f32 * scale).round() as u32).max(1);
+    imageops::resize(&image, w, h, imageops::FilterType::Triangle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::image::Rgba;
+
+    // A file in the temporary directory, removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let file_name = format!("font-{}-{}", std::process::id(), name);
+            Self(std::env::temp_dir().join(file_name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn image(width: u32, height: u32, value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn frame_step() {
+        let step = |source_fps, fps| {
+            AnimationOptions::new(AnimationFormat::Gif)
+                .source_fps(source_fps)
+                .fps(fps)
+                .frame_step()
+        };
+        assert_eq!(step(60.0, 30.0), 2);
+        assert_eq!(step(60.0, 20.0), 3);
+        // rounded to the nearest
+        assert_eq!(step(60.0, 25.0), 2);
+        // frames are never repeated
+        assert_eq!(step(60.0, 60.0), 1);
+        assert_eq!(step(30.0, 60.0), 1);
+    }
+
+    #[test]
+    fn frames_are_put_back_in_order() {
+        let mut frames = FrameOrder::new(1);
+        assert!(frames.push(2, 'c').is_empty());
+        assert!(frames.push(1, 'b').is_empty());
+        assert_eq!(frames.push(0, 'a'), vec!['a', 'b', 'c']);
+        assert_eq!(frames.push(3, 'd'), vec!['d']);
+        assert_eq!(frames.rest().count(), 0);
+    }
+
+    #[test]
+    fn frames_are_skipped_by_the_step() {
+        let mut frames = FrameOrder::new(2);
+        let written: Vec<_> = (0..6).flat_map(|n| frames.push(n, n)).collect();
+        assert_eq!(written, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn lost_frames_are_given_up_on() {
+        // frame 0 never arrives
+        let mut frames = FrameOrder::new(1);
+        let written: Vec<_> = (1..=MAX_PENDING as u32 + 1)
+            .flat_map(|n| frames.push(n, n))
+            .collect();
+        assert_eq!(written, (1..=MAX_PENDING as u32 + 1).collect::<Vec<_>>());
+        assert!(frames.pending.is_empty());
+
+        // too late
+        assert!(frames.push(0, 0).is_empty());
+    }
+
+    #[test]
+    fn rest_is_in_order() {
+        let mut frames = FrameOrder::new(1);
+        frames.push(3, 3);
+        frames.push(2, 2);
+        assert_eq!(frames.rest().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn resize_by_scale() {
+        assert_eq!(resize(image(10, 6, 0), 0.5).dimensions(), (5, 3));
+        assert_eq!(resize(image(10, 6, 0), 1.0).dimensions(), (10, 6));
+        // never empty
+        assert_eq!(resize(image(10, 6, 0), 0.01).dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn apng() {
+        let file = TempFile::new("encoder.png");
+        let options = AnimationOptions::new(AnimationFormat::Apng)
+            .source_fps(60.0)
+            .fps(30.0)
+            .scale(0.5)
+            .plays(Some(3));
+        let encoder = AnimationEncoder::start(file.0.clone(), options);
+        let sender = encoder.sender();
+        for &n in &[2, 1, 0, 4, 3, 5] {
+            sender.send((n, image(8, 4, n as u8 * 10))).unwrap();
+        }
+        drop(sender);
+        encoder.finish().unwrap();
+
+        // the decoder checks the CRC of the patched chunk, too
+        let decoder = png::Decoder::new(File::open(&file.0).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (4, 2));
+        let actl = info.animation_control.unwrap();
+        assert_eq!((actl.num_frames, actl.num_plays), (3, 3));
+
+        // frames 0, 2 and 4
+        let mut buf = vec![0; reader.output_buffer_size()];
+        for &value in &[0, 20, 40] {
+            reader.next_frame(&mut buf).unwrap();
+            assert_eq!(buf[0], value);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use rusttype::{point, Font, PositionedGlyph, Scale};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Lays out multi-line text with rusttype, taking care of line breaks,
+/// kerning, letter spacing and alignment. `Font::layout` only handles a
+/// single line, so this sits in front of `Builder::next_glyph`.
+pub struct TextLayout {
+    scale: Scale,
+    line_height: f32,
+    letter_spacing: f32,
+    align: Align,
+}
+
+impl TextLayout {
+    pub fn new(height: f32) -> Self {
+        Self {
+            scale: Scale::uniform(height),
+            line_height: 1.0,
+            letter_spacing: 0.0,
+            align: Align::Left,
+        }
+    }
+
+    /// The line height as a multiple of the font's natural line advance
+    /// (ascent - descent + line gap).
+    pub fn line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    /// Extra space added after each glyph, in the same unit as the height.
+    pub fn letter_spacing(mut self, letter_spacing: f32) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    fn line_advance(&self, font: &Font) -> f32 {
+        let v_metrics = font.v_metrics(self.scale);
+        (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap) * self.line_height
+    }
+
+    /// The height of the whole text block, from the top of the first line to
+    /// the bottom of the last line.
+    pub fn height(&self, font: &Font, text: &str) -> f32 {
+        let v_metrics = font.v_metrics(self.scale);
+        let n_lines = text.lines().count().max(1);
+        (n_lines - 1) as f32 * self.line_advance(font) + v_metrics.ascent - v_metrics.descent
+    }
+
+    /// Lays out `text` so that the top of the first line is at `top_y`. The
+    /// x coordinate of each line is decided by the alignment: the lines start
+    /// at `x` (left), are centered on `x` (center) or end at `x` (right).
+    ///
+    /// Note that rusttype's y axis points downwards.
+    pub fn layout<'f>(
+        &self,
+        font: &Font<'f>,
+        text: &str,
+        x: f32,
+        top_y: f32,
+    ) -> Vec<PositionedGlyph<'f>> {
+        let v_metrics = font.v_metrics(self.scale);
+        let line_advance = self.line_advance(font);
+
+        let mut result = vec![];
+        for (line_no, line) in text.lines().enumerate() {
+            let baseline = top_y + v_metrics.ascent + line_no as f32 * line_advance;
+
+            let mut glyphs = vec![];
+            let mut caret = 0.0;
+            let mut last_glyph = None;
+            for c in line.chars().filter(|c| !c.is_control()) {
+                let g = font.glyph(c).scaled(self.scale);
+                if let Some(last) = last_glyph {
+                    caret += font.pair_kerning(self.scale, last, g.id());
+                }
+                last_glyph = Some(g.id());
+
+                let advance = g.h_metrics().advance_width;
+                glyphs.push((caret, g));
+                caret += advance + self.letter_spacing;
+            }
+
+            // the spacing after the last glyph doesn't count as the width
+            let width = if glyphs.is_empty() {
+                0.0
+            } else {
+                caret - self.letter_spacing
+            };
+
+            let start_x = match self.align {
+                Align::Left => x,
+                Align::Center => x - width / 2.0,
+                Align::Right => x - width,
+            };
+
+            result.extend(
+                glyphs
+                    .into_iter()
+                    .map(|(caret, g)| g.positioned(point(start_x + caret, baseline))),
+            );
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::test_font;
+
+    const HEIGHT: f32 = 50.0;
+
+    // The glyphs of each line, which share the baseline
+    fn lines<'a, 'f>(glyphs: &'a [PositionedGlyph<'f>]) -> Vec<Vec<&'a PositionedGlyph<'f>>> {
+        let mut lines: Vec<Vec<&PositionedGlyph>> = vec![];
+        for g in glyphs {
+            match lines.last_mut() {
+                Some(line) if line[0].position().y == g.position().y => line.push(g),
+                _ => lines.push(vec![g]),
+            }
+        }
+        lines
+    }
+
+    // From the start of the first glyph to the end of the advance of the last
+    fn extent(line: &[&PositionedGlyph]) -> (f32, f32) {
+        let last = line[line.len() - 1];
+        let end = last.position().x + last.unpositioned().h_metrics().advance_width;
+        (line[0].position().x, end)
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn lines_start_at_x_when_left_aligned() {
+        let font = test_font();
+        let glyphs = TextLayout::new(HEIGHT).layout(&font, "AVA\nWA", 10.0, 0.0);
+        for line in lines(&glyphs) {
+            assert_eq!(line[0].position().x, 10.0);
+        }
+    }
+
+    #[test]
+    fn lines_end_at_x_when_right_aligned() {
+        let font = test_font();
+        // the spacing after the last glyph doesn't push the line to the left
+        let glyphs = TextLayout::new(HEIGHT)
+            .letter_spacing(5.0)
+            .align(Align::Right)
+            .layout(&font, "AVA\nWA", 10.0, 0.0);
+        for line in lines(&glyphs) {
+            assert_near(extent(&line).1, 10.0);
+        }
+    }
+
+    #[test]
+    fn lines_are_centered_on_x() {
+        let font = test_font();
+        let glyphs = TextLayout::new(HEIGHT)
+            .align(Align::Center)
+            .layout(&font, "AVA\nWA", 10.0, 0.0);
+        for line in lines(&glyphs) {
+            let (start, end) = extent(&line);
+            assert_near((start + end) / 2.0, 10.0);
+        }
+    }
+
+    #[test]
+    fn lines_are_a_line_advance_apart() {
+        let font = test_font();
+        let layout = TextLayout::new(HEIGHT).line_height(1.5);
+        let v_metrics = font.v_metrics(Scale::uniform(HEIGHT));
+        let advance = (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap) * 1.5;
+
+        let glyphs = layout.layout(&font, "A\nA\nA", 0.0, 20.0);
+        let baselines: Vec<f32> = lines(&glyphs)
+            .iter()
+            .map(|line| line[0].position().y)
+            .collect();
+        assert_eq!(baselines.len(), 3);
+        assert_near(baselines[0], 20.0 + v_metrics.ascent);
+        assert_near(baselines[1] - baselines[0], advance);
+        assert_near(baselines[2] - baselines[1], advance);
+    }
+
+    #[test]
+    fn height_counts_the_lines() {
+        let font = test_font();
+        let layout = TextLayout::new(HEIGHT);
+        let v_metrics = font.v_metrics(Scale::uniform(HEIGHT));
+        let advance = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+        let one = layout.height(&font, "A");
+        assert_near(one, v_metrics.ascent - v_metrics.descent);
+        assert_near(layout.height(&font, ""), one);
+        assert_near(layout.height(&font, "A\nA\nA"), one + 2.0 * advance);
+    }
+
+    #[test]
+    fn letter_spacing_is_added_after_each_glyph() {
+        let font = test_font();
+        let tight = TextLayout::new(HEIGHT).layout(&font, "ABC", 0.0, 0.0);
+        let loose = TextLayout::new(HEIGHT)
+            .letter_spacing(4.0)
+            .layout(&font, "ABC", 0.0, 0.0);
+        for (i, (t, l)) in tight.iter().zip(&loose).enumerate() {
+            assert_near(l.position().x - t.position().x, 4.0 * i as f32);
+        }
+    }
+
+    #[test]
+    fn control_characters_are_skipped() {
+        let font = test_font();
+        let glyphs = TextLayout::new(HEIGHT).layout(&font, "A\tB", 0.0, 0.0);
+        assert_eq!(glyphs.len(), 2);
+    }
+}
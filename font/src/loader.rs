@@ -0,0 +1,130 @@
+use rusttype::Font;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf),
+    FamilyNotFound(String),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            FontError::Parse(path) => write!(f, "{} is not a valid font file", path.display()),
+            FontError::FamilyNotFound(family) => {
+                write!(f, "no font with family name \"{}\" is found", family)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+/// Loads a font from `name`, which is either a path to a font file or a
+/// family name (e.g. "IPAMincho") of a font installed on the system.
+pub fn load(name: &str) -> Result<Font<'static>, FontError> {
+    let path = Path::new(name);
+    if path.exists() || has_font_extension(path) {
+        load_file(path)
+    } else {
+        load_family(name)
+    }
+}
+
+pub fn load_file(path: &Path) -> Result<Font<'static>, FontError> {
+    let data = std::fs::read(path).map_err(|e| FontError::Io(path.to_path_buf(), e))?;
+    Font::try_from_vec(data).ok_or_else(|| FontError::Parse(path.to_path_buf()))
+}
+
+/// Searches the system font directories for the font whose family name
+/// matches `family` (case-insensitively).
+pub fn load_family(family: &str) -> Result<Font<'static>, FontError> {
+    let mut files = vec![];
+    for dir in font_dirs() {
+        collect_font_files(&dir, &mut files);
+    }
+    // make the result deterministic regardless of the order of readdir
+    files.sort();
+
+    for path in files {
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+
+        let n_fonts = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+        for index in 0..n_fonts {
+            let matched = ttf_parser::Font::from_data(&data, index)
+                .and_then(|f| f.family_name())
+                .is_some_and(|name| name.eq_ignore_ascii_case(family));
+
+            if matched {
+                return Font::try_from_vec_and_index(data, index).ok_or(FontError::Parse(path));
+            }
+        }
+    }
+
+    Err(FontError::FamilyNotFound(family.to_string()))
+}
+
+/// The font the tests are run with, which is checked in with its license
+/// so that the tests don't depend on the fonts installed on the machine.
+/// It has no CJK glyphs, so the tests use Latin text.
+#[cfg(test)]
+pub fn test_font() -> Font<'static> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fonts/DejaVuSans.ttf");
+    load_file(&path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn has_font_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| FONT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// The same directories as fontconfig's defaults, plus the ones of macOS
+// and Windows.
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = vec![
+        "/usr/share/fonts".into(),
+        "/usr/local/share/fonts".into(),
+        "/Library/Fonts".into(),
+        "/System/Library/Fonts".into(),
+        "C:\\Windows\\Fonts".into(),
+    ];
+
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join("Library/Fonts"));
+    }
+
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
+        dirs.push(PathBuf::from(data_home).join("fonts"));
+    }
+
+    dirs
+}
+
+fn collect_font_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_font_files(&path, files);
+        } else if has_font_extension(&path) {
+            files.push(path);
+        }
+    }
+}
@@ -1,9 +1,7 @@
 use nannou::prelude::*;
 
 use rapier2d::data::arena::Index;
-use rapier2d::dynamics::{
-    BallJoint, FixedJoint, IntegrationParameters, JointSet, RigidBodyBuilder, RigidBodySet,
-};
+use rapier2d::dynamics::{IntegrationParameters, JointSet, RigidBodyBuilder, RigidBodySet};
 use rapier2d::geometry::{BroadPhase, ColliderBuilder, ColliderSet, NarrowPhase};
 use rapier2d::na::{Isometry2, Point2, Vector2};
 use rapier2d::pipeline::PhysicsPipeline;
@@ -13,9 +11,78 @@ use lyon::path::Event::*;
 use lyon::path::Path;
 
 use std::f32::consts::PI;
+use std::path::{Path as FilePath, PathBuf};
+
+mod bounds;
+mod camera;
+mod contour;
+mod encoder;
+mod layout;
+mod loader;
+mod recorder;
+mod rigid_glyph;
+mod snapshot;
+mod soft_body;
+mod timestep;
+use bounds::Bounds;
+use camera::Camera;
+use contour::{Contour, ContourKind, Sampling};
+use encoder::{AnimationFormat, AnimationOptions};
+use layout::{Align, TextLayout};
+use recorder::Recorder;
+use rigid_glyph::RigidGlyph;
+use snapshot::{Snapshot, SnapshotRef, Trajectory, TrajectoryWriter};
+use soft_body::{Connection, SoftBodyBuilder, Spring};
+use timestep::{FixedTimestep, Interpolation};
 
 const TOLERANCE: f32 = 0.01;
+// the distance between the particles on the outlines
+const SPACING: f32 = 0.25;
 const HEIGHT: f32 = 10.0;
+const PIXELS_PER_METER: f32 = 40.0;
+// the rate of the physics steps, which is also the frame rate of the recording
+const FRAME_RATE: f32 = 60.0;
+const SUBSTEPS: u32 = 2;
+const RECORDING_FRAMES: u32 = 1000;
+// the margin around the text when fitting the camera to it
+const FIT_PADDING: f32 = 100.0;
+// the settings of the springs bracing the inside of the glyphs
+const STIFFNESS: f32 = 50.0;
+const DAMPING: f32 = 0.5;
+const TEXT: &str = "落";
+
+#[derive(Clone, Copy)]
+enum Mode {
+    /// Each point on the outlines is a particle, connected by joints and springs
+    SoftBody,
+    /// Each glyph is a rigid body, which is much cheaper to simulate
+    Rigid,
+}
+
+/// How the text is turned into bodies, which can be changed on the command
+/// line
+#[derive(Clone, Copy)]
+struct Settings {
+    mode: Mode,
+    align: Align,
+    sampling: Sampling,
+    /// The connections across the inside of each glyph
+    bracing: Connection,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mode: Mode::SoftBody,
+            align: Align::Center,
+            sampling: Sampling::Spacing(SPACING),
+            bracing: Connection::Spring {
+                stiffness: STIFFNESS,
+                damping: DAMPING,
+            },
+        }
+    }
+}
 
 struct Model {
     pipeline: PhysicsPipeline,
@@ -28,9 +95,21 @@ struct Model {
     joints: JointSet,
 
     point_indices: Vec<Vec<Index>>,
-
-    record: bool,
-    recording_frame: u32,
+    springs: Vec<Spring>,
+    rigid_glyphs: Vec<RigidGlyph>,
+    bounds: Bounds,
+    obstacles: Vec<Vec<Point2<f32>>>,
+    camera: Camera,
+    timestep: FixedTimestep,
+    interpolation: Interpolation,
+    // the number of steps simulated
+    frame: u32,
+
+    trajectory_writer: Option<TrajectoryWriter>,
+    replay: Option<Trajectory>,
+
+    recorder: Recorder,
+    texture_capturer: wgpu::TextureCapturer,
 }
 
 struct Builder {
@@ -61,23 +140,24 @@ impl Builder {
         point(x + self.offset_x, self.offset_y - y)
     }
 
+    // rusttype's y axis points downwards, so flip it here so that the
+    // following lines are placed below the previous ones
     fn next_glyph(&mut self, glyph_id: u32, bbox: &rusttype::Rect<i32>) {
         self.cur_glyph_id = glyph_id;
         self.offset_x = bbox.min.x as _;
-        self.offset_y = bbox.max.y as _;
+        self.offset_y = -bbox.min.y as f32;
     }
 
-    fn to_path(self) -> Vec<Vec<Point2<f32>>> {
+    fn into_contours(self) -> Vec<Contour> {
         let path = self.builder.build();
 
         let mut result = vec![];
         let mut points: Vec<Point2<f32>> = vec![];
 
         for p in path.iter_with_attributes() {
-            println!("{:?}", p);
             match p {
-                Begin { .. } => {}
-                Line { from, to } => points.push(Point2::new(to.0.x, to.0.y)),
+                Begin { at } => points.push(Point2::new(at.0.x, at.0.y)),
+                Line { to, .. } => points.push(Point2::new(to.0.x, to.0.y)),
                 Quadratic { from, ctrl, to } => {
                     let seg = lyon::geom::QuadraticBezierSegment {
                         from: from.0,
@@ -106,18 +186,31 @@ impl Builder {
                         points.push(Point2::new(p.x, p.y))
                     }
                 }
-                End { last, first, close } => {
-                    // points.push(Point2::new(last.0.x, last.0.y));
-                    result.push(points.clone());
-                    points.clear();
+                End { last, close, .. } => {
+                    // Fonts often draw the last line back to the start point
+                    // explicitly, which duplicates the first point.
+                    if close && points.len() > 1 && points.first() == points.last() {
+                        points.pop();
+                    }
+
+                    let attrs = last.1;
+                    result.push(Contour {
+                        points: std::mem::take(&mut points),
+                        closed: close,
+                        path_id: attrs[0] as _,
+                        glyph_id: attrs[1] as _,
+                        kind: ContourKind::Outer,
+                    });
                 }
             };
         }
+
+        contour::classify(&mut result);
         result
     }
 }
 
-impl<'a> rusttype::OutlineBuilder for Builder {
+impl rusttype::OutlineBuilder for Builder {
     fn move_to(&mut self, x: f32, y: f32) {
         self.builder.move_to(
             self.point(x, y),
@@ -155,48 +248,232 @@ impl<'a> rusttype::OutlineBuilder for Builder {
     }
 }
 
+// The contours of the same glyph are next to each other, so this returns the
+// range of each glyph.
+fn glyph_ranges(contours: &[Contour]) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    while start < contours.len() {
+        let glyph_id = contours[start].glyph_id;
+        let end = contours[start..]
+            .iter()
+            .position(|c| c.glyph_id != glyph_id)
+            .map_or(contours.len(), |n| start + n);
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
+
 fn main() {
     nannou::app(model)
         .event(event)
         .simple_window(view)
         .update(update)
+        .exit(exit)
         .run();
 }
 
-fn model(_app: &App) -> Model {
-    let font = rusttype::Font::try_from_bytes(include_bytes!(
-        // "/usr/share/fonts/TTF/iosevka-heavyitalic.ttf"
-        // "../fonts/UniHentaiKana-Regular.otf"
-        // "/usr/share/fonts/gsfonts/C059-Roman.otf"
-        "../fonts/ipam.ttf"
-    ))
-    .unwrap();
+fn model(app: &App) -> Model {
+    // The font can be specified either by a path or by a family name, and
+    // the static obstacles can be loaded from a file, e.g.:
+    //
+    //     cargo run --release -- /usr/share/fonts/TTF/iosevka-heavyitalic.ttf
+    //     cargo run --release -- "C059" --obstacles obstacles.txt
+    //
+    // The simulation can start from a snapshot saved by pressing S, and the
+    // trajectory can be logged to a file and replayed later:
+    //
+    //     cargo run --release -- --load snapshots/snapshot-000300.bin
+    //     cargo run --release -- --log run.bin
+    //     cargo run --release -- --replay run.bin
+    //
+    // With `--svg`, the recorded frames are also saved as SVG files, and
+    // with `--rigid`, each glyph is simulated as a rigid body instead of a
+    // soft body. `--align left` (or `right`) aligns the lines of the text
+    // to the left instead of the center, and `--points 20` places 20
+    // particles on each contour instead of spacing them evenly. With
+    // `--no-bracing`, only the neighbouring particles are connected, so the
+    // letters collapse like chains. The animation is a GIF looping forever,
+    // or an APNG with `--apng`, and `--plays 3` makes it play 3 times.
+    let mut font_name = None;
+    let mut obstacles_path = None;
+    let mut load_path = None;
+    let mut log_path = None;
+    let mut replay_path = None;
+    let mut svg = false;
+    let mut settings = Settings::default();
+    let mut animation_format = AnimationFormat::Gif;
+    let mut plays = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--obstacles" => obstacles_path = args.next(),
+            "--load" => load_path = args.next(),
+            "--log" => log_path = args.next(),
+            "--replay" => replay_path = args.next(),
+            "--svg" => svg = true,
+            "--rigid" => settings.mode = Mode::Rigid,
+            "--align" => {
+                settings.align = match args.next().as_deref() {
+                    Some("left") => Align::Left,
+                    Some("center") => Align::Center,
+                    Some("right") => Align::Right,
+                    _ => {
+                        eprintln!("--align needs left, center or right");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--points" => match args.next().map(|n| n.parse()) {
+                Some(Ok(n)) => settings.sampling = Sampling::Count(n),
+                _ => {
+                    eprintln!("--points needs the number of particles per contour");
+                    std::process::exit(1);
+                }
+            },
+            "--no-bracing" => settings.bracing = Connection::None,
+            "--apng" => animation_format = AnimationFormat::Apng,
+            "--plays" => match args.next().map(|n| n.parse()) {
+                Some(Ok(n)) => plays = Some(n),
+                _ => {
+                    eprintln!("--plays needs the number of times to play");
+                    std::process::exit(1);
+                }
+            },
+            _ => font_name = Some(arg),
+        }
+    }
 
-    let scale = rusttype::Scale::uniform(HEIGHT);
-    let v_metrics = font.v_metrics(scale);
-    let offset = rusttype::point(0.0, v_metrics.ascent);
+    // IPA Mincho isn't checked in, as the repo only ships the Latin font
+    // the tests use
+    let default_font = font_name.is_none();
+    let font_name = font_name.unwrap_or_else(|| {
+        app.project_path()
+            .expect("failed to locate `project_path`")
+            .join("fonts/ipam.ttf")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let font = match loader::load(&font_name) {
+        Ok(font) => font,
+        Err(e) => {
+            eprintln!("Failed to load the font: {}", e);
+            if default_font {
+                eprintln!(
+                    "Put IPA Mincho at fonts/ipam.ttf, or pass a font that has {:?}",
+                    TEXT
+                );
+            }
+            std::process::exit(1);
+        }
+    };
+
+    let obstacles = match obstacles_path {
+        Some(path) => match bounds::load_obstacles(std::path::Path::new(&path)) {
+            Ok(obstacles) => obstacles,
+            Err(e) => {
+                eprintln!("Failed to load the obstacles: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => vec![],
+    };
 
-    let mut glyph = font.layout("落", scale, offset);
-    let mut builder = Builder::new(TOLERANCE);
+    let img_dir = app
+        .project_path()
+        .expect("failed to locate `project_path`")
+        .join("img");
+    let mut model = new_model(&font, TEXT, settings, obstacles, app.window_rect(), img_dir);
+
+    if let Some(path) = load_path {
+        match Snapshot::load(FilePath::new(&path)) {
+            Ok(snapshot) => restore(&mut model, snapshot),
+            Err(e) => {
+                eprintln!("Failed to load the snapshot: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    for (glyph_id, g) in glyph.enumerate() {
-        if let Some(bbox) = g.pixel_bounding_box() {
-            // bbox_y.push(bbox.max.y);
-            builder.next_glyph(glyph_id as _, &bbox);
-        } else {
-            continue;
+    if let Some(path) = replay_path {
+        match Trajectory::load(FilePath::new(&path)) {
+            Ok((snapshot, trajectory)) => {
+                println!("Replaying {} frames", trajectory.n_frames());
+                restore(&mut model, snapshot);
+                model.replay = Some(trajectory);
+            }
+            Err(e) => {
+                eprintln!("Failed to load the trajectory: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = log_path {
+        match TrajectoryWriter::create(FilePath::new(&path), &snapshot(&model)) {
+            Ok(writer) => model.trajectory_writer = Some(writer),
+            Err(e) => {
+                eprintln!("Failed to create the trajectory log: {}", e);
+                std::process::exit(1);
+            }
         }
+    }
+
+    // The animation is for sharing
+    let animation = AnimationOptions::new(animation_format)
+        .source_fps(FRAME_RATE)
+        .fps(10.0)
+        .scale(0.5)
+        .plays(plays);
+    model.recorder = model.recorder.svg(svg).animation(Some(animation));
+
+    // Press R to start/stop recording
+    model.recorder.start();
+    model
+}
+
+// Builds everything but the window, so that this can also be used offscreen
+fn new_model(
+    font: &rusttype::Font,
+    text: &str,
+    settings: Settings,
+    obstacles: Vec<Vec<Point2<f32>>>,
+    win: Rect,
+    img_dir: PathBuf,
+) -> Model {
+    let text_layout = TextLayout::new(HEIGHT)
+        .line_height(1.2)
+        .letter_spacing(0.0)
+        .align(settings.align);
+
+    // put the bottom of the text at y = 0
+    let top_y = -text_layout.height(font, text);
+    let glyphs = text_layout.layout(font, text, 0.0, top_y);
+    let mut builder = Builder::new(TOLERANCE);
+
+    for (glyph_id, g) in glyphs.iter().enumerate() {
+        // e.g. a space
+        let bbox = match g.pixel_bounding_box() {
+            Some(bbox) => bbox,
+            None => continue,
+        };
+        builder.next_glyph(glyph_id as _, &bbox);
 
         if !g.build_outline(&mut builder) {
-            println!("empty");
+            eprintln!("The glyph {:?} has no outline, so it's skipped", g.id());
         }
     }
 
-    let font_points = builder.to_path();
+    let font_points = builder.into_contours();
 
     let pipeline = PhysicsPipeline::new();
     let gravity = Vector2::new(0.0, -9.81);
-    let integration_parameters = IntegrationParameters::default();
+    let timestep = FixedTimestep::new(1.0 / FRAME_RATE)
+        .substeps(SUBSTEPS)
+        .max_steps(5);
+    let mut integration_parameters = IntegrationParameters::default();
+    integration_parameters.set_dt(timestep.substep_dt());
     let broad_phase = BroadPhase::new();
     let narrow_phase = NarrowPhase::new();
     let mut bodies = RigidBodySet::new();
@@ -204,60 +481,88 @@ fn model(_app: &App) -> Model {
     let mut joints = JointSet::new();
     // let event_handler = ();
 
-    // Add ground
-    let ground = RigidBodyBuilder::new_static()
-        .position(Isometry2::new(Vector2::new(0.0, -5.0), PI))
-        .build();
-    let idx_ground = bodies.insert(ground);
-    let coll_ground = ColliderBuilder::capsule_x(100.0, 1.0)
-        .friction(0.8)
-        .density(100.0)
-        .build();
-    // ColliderBuilder::segment(Point2::new(-1.0, 0.0), Point2::new(1.0, 0.0)).build();
-    colliders.insert(coll_ground, idx_ground, &mut bodies);
-
-    let mut point_indices = vec![];
-
-    for points in font_points {
-        let mut point_indices_inner = vec![];
-        // Add points
-        for pos in points.iter() {
-            let vec = pos - Point2::origin();
-
-            let rot = (pos - Point2::new(3.2, 2.2)) / 4.0;
+    // Fit the camera to the text so that the text size doesn't matter
+    let mut camera = Camera::new(PIXELS_PER_METER);
+    if let Some((min, max)) = content_bounds(font_points.iter().flat_map(|c| c.points.clone())) {
+        camera.fit(min, max, win, FIT_PADDING);
+    }
 
-            let p = RigidBodyBuilder::new_dynamic()
-                // Note: the unit of position is meter, as I set the gravity to -9.81.
-                .position(Isometry2::new(vec, PI))
-                .linvel(-0.5 - rot[1], 3.0 + rot[0])
-                .build();
+    // Add walls and floor
+    let mut bounds = Bounds::new().floor(true).ceiling(false).walls(true);
+    bounds.build(
+        camera.world_rect(win),
+        &mut bodies,
+        &mut colliders,
+        &mut joints,
+    );
 
-            let idx = bodies.insert(p);
-            let coll = ColliderBuilder::ball(0.1).build();
-            colliders.insert(coll, idx, &mut bodies);
+    for points in &obstacles {
+        bounds::add_obstacle(points, &mut bodies, &mut colliders);
+    }
 
-            point_indices_inner.push(idx);
-        }
+    let contours: Vec<Contour> = font_points
+        .iter()
+        .map(|c| c.resampled(settings.sampling))
+        .collect();
 
-        // Add joints
-        // let p_len = point_indices_inner.len();
-        // for i in 0..p_len {
-        //     let idx = point_indices_inner[i];
-        //     let p = bodies.get(idx).unwrap().position.to_homogeneous();
+    // The PNG frames are for `make out.mp4`
+    let recorder = Recorder::new(img_dir)
+        .frame_range(0, Some(RECORDING_FRAMES))
+        .png(true);
 
-        //     let idx_next = point_indices_inner[(i + 1) % p_len];
-        //     let p_next = bodies.get(idx_next).unwrap().position.to_homogeneous();
+    let mut point_indices = vec![];
+    let mut springs = vec![];
+    let mut rigid_glyphs = vec![];
+
+    match settings.mode {
+        Mode::SoftBody => {
+            for contour in &contours {
+                let mut point_indices_inner = vec![];
+                // Add points
+                for pos in contour.points.iter() {
+                    let vec = pos - Point2::origin();
+
+                    let rot = (pos - Point2::new(3.2, 2.2)) / 4.0;
+
+                    let p = RigidBodyBuilder::new_dynamic()
+                        // Note: the unit of position is meter, as I set the gravity to -9.81.
+                        .position(Isometry2::new(vec, PI))
+                        .linvel(-0.5 - rot[1], 3.0 + rot[0])
+                        .build();
+
+                    let idx = bodies.insert(p);
+                    let coll = ColliderBuilder::ball(0.1).build();
+                    colliders.insert(coll, idx, &mut bodies);
+
+                    point_indices_inner.push(idx);
+                }
 
-        //     // let joint_params = BallJoint::new(
-        //     //     Point2::new(-p[(0, 2)], p[(1, 2)] / 10.0),
-        //     //     Point2::new(p_next[(0, 2)] / 10.0, p_next[(1, 2)] / 10.0),
-        //     // );
-        //     let joint_params = BallJoint::new(Point2::new(3.0, 0.0), Point2::new(0.0, 3.0));
-        //     // let joint_params = FixedJoint::new(p.position, p_next.position);
-        //     joints.insert(&mut bodies, idx, idx_next, joint_params);
-        // }
+                point_indices.push(point_indices_inner);
+            }
 
-        point_indices.push(point_indices_inner);
+            // Add joints and springs per glyph
+            let soft_body_builder = SoftBodyBuilder::new()
+                .ring(Connection::BallJoint)
+                .bracing(settings.bracing);
+
+            for range in glyph_ranges(&contours) {
+                springs.extend(soft_body_builder.build(
+                    &contours[range.clone()],
+                    &point_indices[range],
+                    &mut bodies,
+                    &mut joints,
+                ));
+            }
+        }
+        Mode::Rigid => {
+            // the original outlines are used as the colliders don't need to
+            // be evenly spaced
+            for range in glyph_ranges(&font_points) {
+                let glyph =
+                    RigidGlyph::new(&font_points[range], TOLERANCE, &mut bodies, &mut colliders);
+                rigid_glyphs.extend(glyph);
+            }
+        }
     }
 
     Model {
@@ -269,27 +574,176 @@ fn model(_app: &App) -> Model {
         bodies,
         colliders,
         joints,
+        rigid_glyphs,
+        bounds,
+        obstacles,
+        camera,
+        timestep,
+        interpolation: Interpolation::default(),
+        frame: 0,
+
+        trajectory_writer: None,
+        replay: None,
 
         point_indices,
+        springs,
 
-        record: true,
-        recording_frame: 0,
+        recorder,
+        texture_capturer: wgpu::TextureCapturer::default(),
     }
 }
 
-fn event(_app: &App, _model: &mut Model, _event: Event) {}
+fn event(app: &App, model: &mut Model, event: Event) {
+    let simple = match event {
+        Event::WindowEvent {
+            simple: Some(simple),
+            ..
+        } => simple,
+        _ => return,
+    };
+
+    match simple {
+        // Note that the walls follow the area visible at the time of resizing
+        Resized(_) => model.bounds.build(
+            model.camera.world_rect(app.window_rect()),
+            &mut model.bodies,
+            &mut model.colliders,
+            &mut model.joints,
+        ),
+        MouseWheel(delta, _) => {
+            let dy = match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 20.0,
+            };
+            model.camera.zoom_at(1.1_f32.powf(dy), app.mouse.position());
+        }
+        MousePressed(MouseButton::Left) => model.camera.start_drag(app.mouse.position()),
+        MouseMoved(pos) => model.camera.drag(pos),
+        MouseReleased(MouseButton::Left) => model.camera.end_drag(),
+        KeyPressed(Key::R) => model.recorder.toggle(),
+        KeyPressed(Key::S) => {
+            let dir = app
+                .project_path()
+                .expect("failed to locate `project_path`")
+                .join("snapshots");
+            let path = dir.join(format!("snapshot-{:06}.bin", model.frame));
+            let result = std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("{}: {}", dir.display(), e))
+                .and_then(|_| snapshot(model).save(&path));
+            match result {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(e) => eprintln!("Failed to save the snapshot: {}", e),
+            }
+        }
+        // Save the current frame as an SVG file
+        KeyPressed(Key::V) => {
+            let file_name = format!("font-{}.svg", model.frame);
+            cpu_render::save_svg(app, &file_name, |draw, _| scene(draw, model));
+        }
+        // Fit the camera to the current positions of the bodies
+        KeyPressed(Key::F) => {
+            let positions = model
+                .bodies
+                .iter()
+                .filter(|(_, b)| b.is_dynamic())
+                .map(|(_, b)| Point2::from(b.position.translation.vector));
+            if let Some((min, max)) = content_bounds(positions) {
+                model.camera.fit(min, max, app.window_rect(), FIT_PADDING);
+            }
+        }
+        _ => {}
+    }
+}
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    if model.record {
-        if model.recording_frame >= 999 {
-            println!("Finish recording");
+// The bounding box of the points
+fn content_bounds(points: impl Iterator<Item = Point2<f32>>) -> Option<(Point2<f32>, Point2<f32>)> {
+    points.fold(None, |acc, p| match acc {
+        None => Some((p, p)),
+        Some((min, max)) => Some((
+            Point2::new(min.x.min(p.x), min.y.min(p.y)),
+            Point2::new(max.x.max(p.x), max.y.max(p.y)),
+        )),
+    })
+}
 
-            model.record = false;
-            model.recording_frame = 0;
-        } else {
-            model.recording_frame += 1;
+fn update(_app: &App, model: &mut Model, update: Update) {
+    model.recorder.next_frame();
+
+    // While recording, advance exactly one step per frame so that the
+    // output doesn't depend on how long it takes to render each frame. The
+    // leftover time is dropped, so each frame shows the step itself (see
+    // `scene()`).
+    let steps = if model.recorder.is_recording() {
+        model.timestep.reset();
+        1
+    } else {
+        model.timestep.advance(update.since_last)
+    };
+
+    for _ in 0..steps {
+        model.interpolation.save(&model.bodies);
+
+        // Replaying doesn't simulate anything; the bodies stay at the last
+        // frame after the end, where the recording stops, too
+        if let Some(trajectory) = model.replay.as_mut() {
+            if trajectory.is_finished() {
+                model.recorder.stop();
+                break;
+            }
+            trajectory.next_frame(&mut model.bodies);
+            model.frame += 1;
+            continue;
+        }
+
+        for _ in 0..model.timestep.substeps {
+            step(model);
+        }
+        model.frame += 1;
+
+        if let Some(writer) = model.trajectory_writer.as_mut() {
+            if let Err(e) = writer.write_frame(&model.bodies) {
+                eprintln!("Failed to write the trajectory: {}", e);
+                model.trajectory_writer = None;
+            }
         }
     }
+}
+
+fn snapshot(model: &Model) -> SnapshotRef<'_> {
+    SnapshotRef {
+        frame: model.frame,
+        bodies: &model.bodies,
+        colliders: &model.colliders,
+        joints: &model.joints,
+        broad_phase: &model.broad_phase,
+        narrow_phase: &model.narrow_phase,
+        point_indices: &model.point_indices,
+        springs: &model.springs,
+        rigid_glyphs: &model.rigid_glyphs,
+        bounds: &model.bounds,
+        obstacles: &model.obstacles,
+    }
+}
+
+fn restore(model: &mut Model, snapshot: Snapshot) {
+    model.frame = snapshot.frame;
+    model.bodies = snapshot.bodies;
+    model.colliders = snapshot.colliders;
+    model.joints = snapshot.joints;
+    model.broad_phase = snapshot.broad_phase;
+    model.narrow_phase = snapshot.narrow_phase;
+    model.point_indices = snapshot.point_indices;
+    model.springs = snapshot.springs;
+    model.rigid_glyphs = snapshot.rigid_glyphs;
+    model.bounds = snapshot.bounds;
+    model.obstacles = snapshot.obstacles;
+    model.interpolation = Interpolation::default();
+}
+
+fn step(model: &mut Model) {
+    for spring in &model.springs {
+        spring.apply(&mut model.bodies);
+    }
 
     model.pipeline.step(
         &model.gravity,
@@ -303,8 +757,15 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     );
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn scene(draw: &cpu_render::Draw, model: &Model) {
+    let camera = &model.camera;
+    // A recorded frame is always right after a step
+    let alpha = if model.recorder.is_recording() {
+        1.0
+    } else {
+        model.timestep.alpha()
+    };
+    let position = |idx| model.interpolation.position(idx, &model.bodies, alpha);
 
     draw.background().color(nannou::color::rgb_u32(0xDEC2CB));
 
@@ -314,11 +775,11 @@ fn view(app: &App, model: &Model, frame: Frame) {
             let idx = point_indices[i];
 
             // add point
-            let p = model.bodies.get(idx).unwrap();
-            let p_homo = p.position.to_homogeneous() * 40.0;
+            let p = position(idx);
+            let p_screen = camera.to_screen(&Point2::from(p.translation.vector));
             draw.ellipse()
-                .x_y(p_homo[(0, 2)], p_homo[(1, 2)])
-                .radius(4.5)
+                .xy(p_screen)
+                .radius(4.5 * camera.zoom)
                 .color(nannou::color::rgb_u32(0x91163D));
 
             // if there's only one point, there's no lines
@@ -327,30 +788,230 @@ fn view(app: &App, model: &Model, frame: Frame) {
             }
 
             let idx_next = point_indices[(i + 1) % p_len];
-            let p_next = model.bodies.get(idx_next).unwrap();
-            let p_next_homo = p_next.position.to_homogeneous() * 40.0;
+            let p_next = position(idx_next);
+            let p_next_screen = camera.to_screen(&Point2::from(p_next.translation.vector));
             draw.line()
-                .start(pt2(p_homo[(0, 2)], p_homo[(1, 2)]))
-                .end(pt2(p_next_homo[(0, 2)], p_next_homo[(1, 2)]))
-                .weight(3.2)
+                .start(p_screen)
+                .end(p_next_screen)
+                .weight(3.2 * camera.zoom)
                 .color(nannou::color::rgb_u32(0x91163D));
         }
     }
 
+    for points in &model.obstacles {
+        draw.polyline()
+            .weight(3.2 * camera.zoom)
+            .points(points.iter().map(|p| camera.to_screen(p)))
+            .color(nannou::color::rgb_u32(0x5A2E3C));
+    }
+
+    for glyph in &model.rigid_glyphs {
+        let body_position = position(glyph.body);
+        for contour in &glyph.contours {
+            let mut points: Vec<Point2<f32>> =
+                contour.points.iter().map(|p| body_position * p).collect();
+            if contour.closed {
+                points.extend(points.first().cloned());
+            }
+
+            draw.polyline()
+                .weight(3.2 * camera.zoom)
+                .points(points.iter().map(|p| camera.to_screen(p)))
+                .color(nannou::color::rgb_u32(0x91163D));
+        }
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, model);
+
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 
     // Capture the frame!
-    if model.record {
-        let file_path = captured_frame_path(app, model.recording_frame);
+    if let Some(file_path) = model.recorder.frame_path() {
         app.main_window().capture_frame(file_path);
     }
+
+    if let Some(file_path) = model.recorder.svg_path() {
+        if let Err(e) = svg_writer(app.window_rect()).save(&scene_draw, file_path) {
+            eprintln!("Failed to save the SVG: {}", e);
+        }
+    }
+
+    // Capture the frame for the animation in memory
+    if let Some((n, sender)) = model.recorder.animation_frame() {
+        let device = frame.device_queue_pair().device();
+        let snapshot =
+            model
+                .texture_capturer
+                .capture(device, &mut frame.command_encoder(), frame.texture());
+        snapshot.read_threaded(move |result| match result {
+            Ok(image) => {
+                let _ = sender.send((n, image));
+            }
+            Err(_) => eprintln!("Failed to capture frame {}", n),
+        });
+    }
 }
 
-fn captured_frame_path(app: &App, frame: u32) -> std::path::PathBuf {
-    // Create a path that we want to save this frame to.
-    app.project_path()
-        .expect("failed to locate `project_path`")
-        .join("img")
-        .join(format!("{:03}", frame))
-        .with_extension("png")
+fn svg_writer(win: Rect) -> cpu_render::Svg {
+    cpu_render::Svg::new(win.w().round() as u32, win.h().round() as u32)
+}
+
+fn exit(app: &App, mut model: Model) {
+    // Wait for the captures and the encoding to complete
+    app.main_window().swap_chain_device().poll(true);
+    model.texture_capturer.finish();
+
+    model.recorder.stop();
+    model.recorder.wait();
+
+    if let Some(writer) = model.trajectory_writer.take() {
+        if let Err(e) = writer.finish() {
+            eprintln!("Failed to write the trajectory: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpu_render::Input;
+
+    // The number of frames to simulate before rendering
+    const FRAMES: u32 = 120;
+    // The test font has no glyph for TEXT. Both letters have a hole.
+    const TEST_TEXT: &str = "Ag";
+
+    // An "o" of two squares, the inner one wound the other way round, in
+    // rusttype's coordinates
+    fn letter_o(builder: &mut Builder) {
+        use rusttype::OutlineBuilder;
+
+        builder.move_to(0.0, 0.0);
+        builder.line_to(10.0, 0.0);
+        builder.line_to(10.0, 10.0);
+        builder.line_to(0.0, 10.0);
+        builder.close();
+
+        builder.move_to(3.0, 3.0);
+        builder.line_to(3.0, 7.0);
+        builder.line_to(7.0, 7.0);
+        builder.line_to(7.0, 3.0);
+        builder.close();
+    }
+
+    #[test]
+    fn contours_of_a_letter_with_a_hole() {
+        let mut builder = Builder::new(0.1);
+        letter_o(&mut builder);
+        let contours = builder.into_contours();
+
+        assert_eq!(contours.len(), 2);
+        assert_eq!(contours[0].kind, ContourKind::Outer);
+        assert_eq!(contours[1].kind, ContourKind::Hole);
+        for (i, c) in contours.iter().enumerate() {
+            assert!(c.closed);
+            assert_eq!(c.points.len(), 4);
+            assert_eq!(c.path_id, i as u32);
+        }
+        // the starting point is kept, with the y axis flipped
+        assert_eq!(contours[0].points[0], Point2::new(0.0, 0.0));
+        assert_eq!(contours[1].points[0], Point2::new(3.0, -3.0));
+    }
+
+    #[test]
+    fn contours_keep_the_glyph() {
+        let mut builder = Builder::new(0.1);
+        let bbox = rusttype::Rect {
+            min: rusttype::point(0, 0),
+            max: rusttype::point(10, 10),
+        };
+        builder.next_glyph(3, &bbox);
+        letter_o(&mut builder);
+        builder.next_glyph(4, &bbox);
+        letter_o(&mut builder);
+        let contours = builder.into_contours();
+
+        let glyph_ids: Vec<_> = contours.iter().map(|c| c.glyph_id).collect();
+        assert_eq!(glyph_ids, vec![3, 3, 4, 4]);
+        let kinds: Vec<_> = contours.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ContourKind::Outer,
+                ContourKind::Hole,
+                ContourKind::Outer,
+                ContourKind::Hole
+            ]
+        );
+    }
+
+    #[test]
+    fn open_contours_are_not_closed() {
+        use rusttype::OutlineBuilder;
+
+        let mut builder = Builder::new(0.1);
+        builder.move_to(0.0, 0.0);
+        builder.line_to(10.0, 0.0);
+        builder.line_to(10.0, 10.0);
+        let contours = builder.into_contours();
+
+        assert_eq!(contours.len(), 1);
+        assert!(!contours[0].closed);
+        assert_eq!(contours[0].points.len(), 3);
+    }
+
+    #[test]
+    fn closing_line_back_to_the_start_is_dropped() {
+        use rusttype::OutlineBuilder;
+
+        let mut builder = Builder::new(0.1);
+        builder.move_to(0.0, 0.0);
+        builder.line_to(10.0, 0.0);
+        builder.line_to(10.0, 10.0);
+        builder.line_to(0.0, 0.0);
+        builder.close();
+        let contours = builder.into_contours();
+
+        assert_eq!(contours.len(), 1);
+        assert!(contours[0].closed);
+        assert_eq!(
+            contours[0].points,
+            vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(10.0, 0.0),
+                Point2::new(10.0, -10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn golden() {
+        let font = loader::test_font();
+
+        let input = Input::new(1024, 768);
+        let img_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("img");
+        let mut model = new_model(
+            &font,
+            TEST_TEXT,
+            Settings::default(),
+            vec![],
+            input.window_rect,
+            img_dir,
+        );
+
+        // Advance exactly one step per frame, as when recording
+        for _ in 0..FRAMES {
+            model.interpolation.save(&model.bodies);
+            for _ in 0..model.timestep.substeps {
+                step(&mut model);
+            }
+        }
+
+        cpu_render::assert_scene("font", &input, |draw, _| scene(draw, &model));
+    }
 }
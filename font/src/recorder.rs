@@ -0,0 +1,318 @@
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::encoder::{AnimationEncoder, AnimationOptions, FrameSender};
+
+// The width of the frame numbers when the end of the range is not specified
+const DEFAULT_DIGITS: usize = 6;
+
+/// Captures frames into a per-session subdirectory of `output_dir`, so that
+/// repeated runs don't overwrite each other's frames. The frames are saved
+/// as PNG files, SVG files and/or encoded into an animation.
+pub struct Recorder {
+    output_dir: PathBuf,
+    start_frame: u32,
+    end_frame: Option<u32>,
+    png: bool,
+    svg: bool,
+    animation: Option<AnimationOptions>,
+    recording: bool,
+    // None until the first frame of the session
+    frame: Option<u32>,
+    session_dir: Option<PathBuf>,
+    encoder: Option<AnimationEncoder>,
+    // the encoders that are finishing in the background
+    finishing: Vec<JoinHandle<()>>,
+}
+
+impl Recorder {
+    pub fn new<P: AsRef<Path>>(output_dir: P) -> Self {
+        Self {
+            output_dir: output_dir.as_ref().to_path_buf(),
+            start_frame: 0,
+            end_frame: None,
+            png: true,
+            svg: false,
+            animation: None,
+            recording: false,
+            frame: None,
+            session_dir: None,
+            encoder: None,
+            finishing: vec![],
+        }
+    }
+
+    /// Captures only the frames from `start` (inclusive) to `end`
+    /// (exclusive), counted from the start of the recording. The recording
+    /// stops automatically after `end`.
+    pub fn frame_range(mut self, start: u32, end: Option<u32>) -> Self {
+        self.start_frame = start;
+        self.end_frame = end;
+        self
+    }
+
+    /// Whether to save each frame as a PNG file
+    pub fn png(mut self, png: bool) -> Self {
+        self.png = png;
+        self
+    }
+
+    /// Whether to save each frame as an SVG file, which needs to be written
+    /// by the sketch itself
+    pub fn svg(mut self, svg: bool) -> Self {
+        self.svg = svg;
+        self
+    }
+
+    /// Encodes the frames into an animation, which is saved in the session
+    /// directory (e.g. `out.gif`) when the recording stops.
+    pub fn animation(mut self, animation: Option<AnimationOptions>) -> Self {
+        self.animation = animation;
+        self
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn start(&mut self) {
+        if self.recording {
+            return;
+        }
+
+        let session_dir = self.new_session_dir();
+        if let Err(e) = std::fs::create_dir_all(&session_dir) {
+            eprintln!("Failed to create {}: {}", session_dir.display(), e);
+            return;
+        }
+        println!("Start recording to {}", session_dir.display());
+
+        self.encoder = self.animation.map(|options| {
+            let path = session_dir
+                .join("out")
+                .with_extension(options.format.extension());
+            AnimationEncoder::start(path, options)
+        });
+        self.session_dir = Some(session_dir);
+        self.frame = None;
+        self.recording = true;
+    }
+
+    pub fn stop(&mut self) {
+        if self.recording {
+            println!("Finish recording");
+        }
+        self.recording = false;
+
+        // The encoder waits for the frames still being captured, so don't
+        // block the main thread, which is needed to complete the capture.
+        if let Some(encoder) = self.encoder.take() {
+            self.finishing.push(std::thread::spawn(move || {
+                if let Err(e) = encoder.finish() {
+                    eprintln!("Failed to encode the animation: {}", e);
+                }
+            }));
+        }
+    }
+
+    /// Waits for the animations to be written. This should be called after
+    /// all the captures are done, e.g. on exit.
+    pub fn wait(&mut self) {
+        for handle in self.finishing.drain(..) {
+            let _ = handle.join();
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if self.recording {
+            self.stop();
+        } else {
+            self.start();
+        }
+    }
+
+    /// Advances the frame counter. This should be called once per frame.
+    pub fn next_frame(&mut self) {
+        if !self.recording {
+            return;
+        }
+
+        let frame = self.frame.map_or(0, |f| f + 1);
+        self.frame = Some(frame);
+
+        if let Some(end) = self.end_frame {
+            if frame >= end {
+                self.stop();
+            }
+        }
+    }
+
+    /// The path to save the current frame to, or `None` if the current frame
+    /// shouldn't be captured.
+    pub fn frame_path(&self) -> Option<PathBuf> {
+        if !self.png {
+            return None;
+        }
+        self.frame_file("png")
+    }
+
+    /// The same as `frame_path()`, but for the SVG file
+    pub fn svg_path(&self) -> Option<PathBuf> {
+        if !self.svg {
+            return None;
+        }
+        self.frame_file("svg")
+    }
+
+    fn frame_file(&self, extension: &str) -> Option<PathBuf> {
+        if !self.recording {
+            return None;
+        }
+
+        let frame = self.frame?;
+        if frame < self.start_frame {
+            return None;
+        }
+
+        // The frame numbers are zero-padded to the same width so that they
+        // are sorted correctly by name (e.g. by ffmpeg's glob pattern).
+        let digits = match self.end_frame {
+            Some(end) => end.saturating_sub(1).to_string().len(),
+            None => DEFAULT_DIGITS,
+        };
+
+        let session_dir = self.session_dir.as_ref()?;
+        Some(
+            session_dir
+                .join(format!("{:0width$}", frame, width = digits))
+                .with_extension(extension),
+        )
+    }
+
+    /// The frame number (counted from the start of the range) and where to
+    /// send the current frame for the animation, or `None` if the current
+    /// frame shouldn't be encoded.
+    pub fn animation_frame(&self) -> Option<(u32, FrameSender)> {
+        if !self.recording {
+            return None;
+        }
+
+        let frame = self.frame?;
+        if frame < self.start_frame {
+            return None;
+        }
+
+        let encoder = self.encoder.as_ref()?;
+        Some((frame - self.start_frame, encoder.sender()))
+    }
+
+    fn new_session_dir(&self) -> PathBuf {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.session_dir_at(secs)
+    }
+
+    // e.g. img/session-1602900000, or img/session-1602900000-2 if it already
+    // exists
+    fn session_dir_at(&self, secs: u64) -> PathBuf {
+        let base = format!("session-{}", secs);
+
+        let mut dir = self.output_dir.join(&base);
+        let mut n = 2;
+        while dir.exists() {
+            dir = self.output_dir.join(format!("{}-{}", base, n));
+            n += 1;
+        }
+        dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory in the temporary directory, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir_name = format!("font-{}-{}", std::process::id(), name);
+            Self(std::env::temp_dir().join(dir_name))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // The file names of the frames captured while recording `n_frames`
+    fn captured(recorder: &mut Recorder, n_frames: u32) -> Vec<Option<String>> {
+        recorder.start();
+        (0..n_frames)
+            .map(|_| {
+                recorder.next_frame();
+                recorder
+                    .frame_path()
+                    .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frame_range() {
+        let dir = TempDir::new("recorder-range");
+        let mut recorder = Recorder::new(&dir.0).frame_range(2, Some(5));
+
+        let names = captured(&mut recorder, 6);
+        let expected = vec![
+            None,
+            None,
+            Some("2.png".to_string()),
+            Some("3.png".to_string()),
+            Some("4.png".to_string()),
+            // stopped at the end of the range
+            None,
+        ];
+        assert_eq!(names, expected);
+        assert!(!recorder.is_recording());
+    }
+
+    #[test]
+    fn frame_digits() {
+        let dir = TempDir::new("recorder-digits");
+
+        // wide enough for the last frame, 99
+        let mut recorder = Recorder::new(&dir.0).frame_range(0, Some(100));
+        assert_eq!(captured(&mut recorder, 1), vec![Some("00.png".to_string())]);
+        recorder.stop();
+
+        let mut recorder = Recorder::new(&dir.0).frame_range(0, Some(101));
+        assert_eq!(
+            captured(&mut recorder, 1),
+            vec![Some("000.png".to_string())]
+        );
+        recorder.stop();
+
+        let mut recorder = Recorder::new(&dir.0);
+        assert_eq!(
+            captured(&mut recorder, 1),
+            vec![Some("000000.png".to_string())]
+        );
+        recorder.stop();
+    }
+
+    #[test]
+    fn session_dirs_are_not_reused() {
+        let dir = TempDir::new("recorder-sessions");
+        let recorder = Recorder::new(&dir.0);
+
+        for expected in &["session-42", "session-42-2", "session-42-3"] {
+            let session_dir = recorder.session_dir_at(42);
+            assert_eq!(session_dir, dir.0.join(expected));
+            std::fs::create_dir_all(&session_dir).unwrap();
+        }
+    }
+}
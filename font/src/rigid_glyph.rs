@@ -0,0 +1,253 @@
+use rapier2d::data::arena::Index;
+use rapier2d::dynamics::{RigidBodyBuilder, RigidBodySet};
+use rapier2d::geometry::{ColliderBuilder, ColliderSet, Polygon, Shape};
+use rapier2d::na::{Isometry2, Point2, Vector2};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::contour::{self, Contour};
+
+/// A glyph simulated as a single rigid body. The collider is a compound of
+/// convex pieces, which are attached to the body as separate colliders.
+#[derive(Serialize, Deserialize)]
+pub struct RigidGlyph {
+    pub body: Index,
+    /// The contours in the local coordinates of the body
+    pub contours: Vec<Contour>,
+}
+
+impl RigidGlyph {
+    /// `contours` are the contours of one glyph in the world coordinates.
+    pub fn new(
+        contours: &[Contour],
+        tolerance: f32,
+        bodies: &mut RigidBodySet,
+        colliders: &mut ColliderSet,
+    ) -> Option<Self> {
+        let geometry = contour::triangulate(contours, tolerance);
+        if geometry.indices.is_empty() {
+            return None;
+        }
+
+        // Place the body at the center of the vertices so that it rotates
+        // around the middle of the glyph.
+        let center = geometry
+            .vertices
+            .iter()
+            .fold(Vector2::zeros(), |acc, p| acc + p.coords)
+            / geometry.vertices.len() as f32;
+
+        let body = RigidBodyBuilder::new_dynamic()
+            .position(Isometry2::new(center, 0.0))
+            .build();
+        let body = bodies.insert(body);
+
+        for piece in convex_decomposition(&geometry.vertices, &geometry.indices) {
+            let points: Vec<Point2<f32>> = piece
+                .iter()
+                .map(|&i| geometry.vertices[i as usize] - center)
+                .collect();
+
+            if let Some(coll) = convex_polygon(points) {
+                colliders.insert(coll.friction(0.8).build(), body, bodies);
+            }
+        }
+
+        let contours = contours
+            .iter()
+            .map(|c| Contour {
+                points: c.points.iter().map(|p| p - center).collect(),
+                closed: c.closed,
+                path_id: c.path_id,
+                glyph_id: c.glyph_id,
+                kind: c.kind,
+            })
+            .collect();
+
+        Some(Self { body, contours })
+    }
+}
+
+// rapier doesn't compute convex hulls yet, but the pieces are convex and
+// counter-clockwise already, so they only need the outward normals of their
+// edges.
+fn convex_polygon(mut points: Vec<Point2<f32>>) -> Option<ColliderBuilder> {
+    points.dedup_by(|a, b| (*a - *b).norm() < 1e-6);
+    while points.len() > 1 && (points[0] - points[points.len() - 1]).norm() < 1e-6 {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return None;
+    }
+
+    let n = points.len();
+    let normals = (0..n)
+        .map(|i| {
+            let d = points[(i + 1) % n] - points[i];
+            Vector2::new(d.y, -d.x).normalize()
+        })
+        .collect();
+    Some(ColliderBuilder::new(Shape::Polygon(Polygon::new(
+        points, normals,
+    ))))
+}
+
+/// Merges the triangles into convex polygons by the Hertel-Mehlhorn
+/// algorithm: each diagonal between two triangles is visited once and
+/// removed if the merged polygon stays convex at both of its ends. The
+/// result is at most four times as many pieces as the optimal
+/// decomposition, which is good enough for colliders.
+pub fn convex_decomposition(vertices: &[Point2<f32>], indices: &[u32]) -> Vec<Vec<u32>> {
+    let triangles: Vec<Vec<u32>> = indices
+        .chunks_exact(3)
+        .map(|tri| {
+            let mut tri = tri.to_vec();
+            // make all the polygons counter-clockwise
+            let points: Vec<Point2<f32>> = tri.iter().map(|&i| vertices[i as usize]).collect();
+            if contour::signed_area(&points) < 0.0 {
+                tri.reverse();
+            }
+            tri
+        })
+        .collect();
+
+    // The diagonals are the edges shared by two triangles. As both are
+    // counter-clockwise, an edge a -> b of one appears as b -> a in the
+    // other.
+    let mut edges = HashMap::new();
+    for (t, tri) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            edges.insert((tri[k], tri[(k + 1) % 3]), t);
+        }
+    }
+    let mut diagonals: Vec<(u32, u32, usize, usize)> = edges
+        .iter()
+        .filter(|(&(a, b), _)| a < b)
+        .filter_map(|(&(a, b), &t)| edges.get(&(b, a)).map(|&u| (a, b, t, u)))
+        .collect();
+    // in a fixed order so that the colliders are the same on every run
+    diagonals.sort_unstable();
+
+    // Each triangle belongs to the polygon found by following `merged_into`
+    let mut polygons: Vec<Option<Vec<u32>>> = triangles.into_iter().map(Some).collect();
+    let mut merged_into: Vec<usize> = (0..polygons.len()).collect();
+
+    for (a, b, t, u) in diagonals {
+        let (p, q) = (find(&mut merged_into, t), find(&mut merged_into, u));
+        if p == q {
+            continue;
+        }
+
+        let merged = match (&polygons[p], &polygons[q]) {
+            (Some(p), Some(q)) => try_merge(vertices, p, q, a, b),
+            _ => None,
+        };
+        if let Some(polygon) = merged {
+            polygons[p] = Some(polygon);
+            polygons[q] = None;
+            merged_into[q] = p;
+        }
+    }
+
+    polygons.into_iter().flatten().collect()
+}
+
+// The polygon a triangle belongs to, compressing the path on the way
+fn find(merged_into: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while merged_into[root] != root {
+        root = merged_into[root];
+    }
+
+    let mut i = i;
+    while merged_into[i] != root {
+        let next = merged_into[i];
+        merged_into[i] = root;
+        i = next;
+    }
+    root
+}
+
+// Merges the polygons along the edge a -> b of p, which is b -> a in q, if
+// the result is convex. The other corners are convex already, so only the
+// corners at a and b are checked.
+fn try_merge(vertices: &[Point2<f32>], p: &[u32], q: &[u32], a: u32, b: u32) -> Option<Vec<u32>> {
+    let (n_p, n_q) = (p.len(), q.len());
+    let i = (0..n_p).find(|&i| p[i] == a && p[(i + 1) % n_p] == b)?;
+    let j = (0..n_q).find(|&j| q[j] == b && q[(j + 1) % n_q] == a)?;
+
+    // walk p from b around to a, then q from a around to b (excluding both
+    // ends as they are already added)
+    let mut polygon: Vec<u32> = (0..n_p).map(|k| p[(i + 1 + k) % n_p]).collect();
+    polygon.extend((2..n_q).map(|k| q[(j + k) % n_q]));
+
+    // b is the first vertex and a is the last one of p's part
+    if is_convex_at(vertices, &polygon, 0) && is_convex_at(vertices, &polygon, n_p - 1) {
+        Some(polygon)
+    } else {
+        None
+    }
+}
+
+// Whether the corner at the k-th vertex turns left
+fn is_convex_at(vertices: &[Point2<f32>], polygon: &[u32], k: usize) -> bool {
+    let n = polygon.len();
+    let p0 = vertices[polygon[(k + n - 1) % n] as usize];
+    let p1 = vertices[polygon[k] as usize];
+    let p2 = vertices[polygon[(k + 1) % n] as usize];
+    let (d1, d2) = (p1 - p0, p2 - p1);
+    d1.x * d2.y - d1.y * d2.x >= -1e-6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_convex(vertices: &[Point2<f32>], polygon: &[u32]) -> bool {
+        (0..polygon.len()).all(|k| is_convex_at(vertices, polygon, k))
+    }
+
+    #[test]
+    fn square_is_one_piece() {
+        let vertices = [
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+        // the second triangle is clockwise
+        let pieces = convex_decomposition(&vertices, &[0, 1, 2, 0, 3, 2]);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].len(), 4);
+        assert!(is_convex(&vertices, &pieces[0]));
+    }
+
+    #[test]
+    fn l_shape_is_two_pieces() {
+        //  5--4
+        //  |  |
+        //  |  3--2
+        //  |     |
+        //  0-----1
+        let vertices = [
+            Point2::new(0.0, 0.0),
+            Point2::new(2.0, 0.0),
+            Point2::new(2.0, 1.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(1.0, 2.0),
+            Point2::new(0.0, 2.0),
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 0, 3, 5, 3, 4, 5];
+        let pieces = convex_decomposition(&vertices, &indices);
+
+        assert_eq!(pieces.len(), 2);
+        for piece in &pieces {
+            assert!(is_convex(&vertices, piece), "{:?}", piece);
+        }
+        // no triangle is lost
+        let n_vertices: usize = pieces.iter().map(|p| p.len()).sum();
+        assert_eq!(n_vertices, 4 + 2 + 2);
+    }
+}
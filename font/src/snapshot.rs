@@ -0,0 +1,343 @@
+use rapier2d::data::arena::Index;
+use rapier2d::dynamics::{JointSet, RigidBodySet};
+use rapier2d::geometry::{BroadPhase, ColliderSet, NarrowPhase};
+use rapier2d::na::{Isometry2, Point2, Vector2};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::bounds::Bounds;
+use crate::rigid_glyph::RigidGlyph;
+use crate::soft_body::Spring;
+
+/// The whole state of the simulation at a frame, including the broad phase
+/// and the narrow phase so that the simulation continues exactly as it would
+/// have.
+#[derive(Deserialize)]
+pub struct Snapshot {
+    pub frame: u32,
+    pub bodies: RigidBodySet,
+    pub colliders: ColliderSet,
+    pub joints: JointSet,
+    pub broad_phase: BroadPhase,
+    pub narrow_phase: NarrowPhase,
+    pub point_indices: Vec<Vec<Index>>,
+    pub springs: Vec<Spring>,
+    pub rigid_glyphs: Vec<RigidGlyph>,
+    pub bounds: Bounds,
+    pub obstacles: Vec<Vec<Point2<f32>>>,
+}
+
+/// The same as `Snapshot` but borrows the state, so that saving doesn't
+/// need to clone it. The fields must be in the same order as `Snapshot`.
+#[derive(Serialize)]
+pub struct SnapshotRef<'a> {
+    pub frame: u32,
+    pub bodies: &'a RigidBodySet,
+    pub colliders: &'a ColliderSet,
+    pub joints: &'a JointSet,
+    pub broad_phase: &'a BroadPhase,
+    pub narrow_phase: &'a NarrowPhase,
+    pub point_indices: &'a [Vec<Index>],
+    pub springs: &'a [Spring],
+    pub rigid_glyphs: &'a [RigidGlyph],
+    pub bounds: &'a Bounds,
+    pub obstacles: &'a [Vec<Point2<f32>>],
+}
+
+impl<'a> SnapshotRef<'a> {
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        let mut w = BufWriter::new(File::create(path).map_err(|e| err(&e))?);
+        bincode::serialize_into(&mut w, self).map_err(|e| err(&e))?;
+        w.flush().map_err(|e| err(&e))
+    }
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        let r = BufReader::new(File::open(path).map_err(|e| err(&e))?);
+        bincode::deserialize_from(r).map_err(|e| err(&e))
+    }
+}
+
+/// The position and the angle of a body
+type Pose = [f32; 3];
+
+/// Writes the positions of the dynamic bodies at every frame. The log starts
+/// with a snapshot, which provides everything else needed to render, so the
+/// log can be replayed on its own.
+///
+/// The layout is a `Snapshot`, followed by the handles of the logged bodies,
+/// followed by the poses of the bodies at each frame in the same order.
+pub struct TrajectoryWriter {
+    w: BufWriter<File>,
+    handles: Vec<Index>,
+}
+
+impl TrajectoryWriter {
+    pub fn create(path: &Path, snapshot: &SnapshotRef) -> Result<Self, String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        let handles: Vec<Index> = snapshot
+            .bodies
+            .iter()
+            .filter(|(_, b)| b.is_dynamic())
+            .map(|(handle, _)| handle)
+            .collect();
+
+        let mut w = BufWriter::new(File::create(path).map_err(|e| err(&e))?);
+        bincode::serialize_into(&mut w, snapshot).map_err(|e| err(&e))?;
+        bincode::serialize_into(&mut w, &handles).map_err(|e| err(&e))?;
+
+        Ok(Self { w, handles })
+    }
+
+    /// Appends the current poses. This should be called after each step.
+    pub fn write_frame(&mut self, bodies: &RigidBodySet) -> Result<(), String> {
+        let poses: Vec<Pose> = self
+            .handles
+            .iter()
+            .map(|&handle| match bodies.get(handle) {
+                Some(body) => {
+                    let t = body.position.translation.vector;
+                    [t.x, t.y, body.position.rotation.angle()]
+                }
+                None => [f32::NAN; 3],
+            })
+            .collect();
+
+        bincode::serialize_into(&mut self.w, &poses).map_err(|e| e.to_string())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.w.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// A trajectory log loaded to replay
+pub struct Trajectory {
+    handles: Vec<Index>,
+    frames: Vec<Vec<Pose>>,
+    next: usize,
+}
+
+impl Trajectory {
+    /// Returns the snapshot at the start of the log, too.
+    pub fn load(path: &Path) -> Result<(Snapshot, Self), String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        let mut r = BufReader::new(File::open(path).map_err(|e| err(&e))?);
+        let snapshot: Snapshot = bincode::deserialize_from(&mut r).map_err(|e| err(&e))?;
+        let handles: Vec<Index> = bincode::deserialize_from(&mut r).map_err(|e| err(&e))?;
+
+        let mut frames = vec![];
+        loop {
+            match bincode::deserialize_from::<_, Vec<Pose>>(&mut r) {
+                Ok(poses) => frames.push(poses),
+                Err(e) => match *e {
+                    bincode::ErrorKind::Io(ref io)
+                        if io.kind() == std::io::ErrorKind::UnexpectedEof =>
+                    {
+                        break
+                    }
+                    _ => return Err(err(&e)),
+                },
+            }
+        }
+
+        let trajectory = Self {
+            handles,
+            frames,
+            next: 0,
+        };
+        Ok((snapshot, trajectory))
+    }
+
+    pub fn n_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+
+    /// Moves the bodies to the poses of the next frame. Does nothing once
+    /// the trajectory is finished.
+    pub fn next_frame(&mut self, bodies: &mut RigidBodySet) {
+        let poses = match self.frames.get(self.next) {
+            Some(poses) => poses,
+            None => return,
+        };
+
+        for (&handle, pose) in self.handles.iter().zip(poses) {
+            if let Some(mut body) = bodies.get_mut(handle) {
+                body.set_position(Isometry2::new(Vector2::new(pose[0], pose[1]), pose[2]));
+            }
+        }
+
+        self.next += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapier2d::dynamics::RigidBodyBuilder;
+
+    use std::path::PathBuf;
+
+    // A file in the temporary directory, removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let file_name = format!("font-{}-{}", std::process::id(), name);
+            Self(std::env::temp_dir().join(file_name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    struct World {
+        bodies: RigidBodySet,
+        colliders: ColliderSet,
+        joints: JointSet,
+        broad_phase: BroadPhase,
+        narrow_phase: NarrowPhase,
+        point_indices: Vec<Vec<Index>>,
+        bounds: Bounds,
+        obstacles: Vec<Vec<Point2<f32>>>,
+    }
+
+    impl World {
+        // Two particles and a static body
+        fn new() -> Self {
+            let mut bodies = RigidBodySet::new();
+            let mut particle = |x: f32| {
+                let body = RigidBodyBuilder::new_dynamic()
+                    .position(Isometry2::new(Vector2::new(x, 1.0), 0.0))
+                    .build();
+                bodies.insert(body)
+            };
+            let point_indices = vec![vec![particle(0.0), particle(1.0)]];
+            bodies.insert(RigidBodyBuilder::new_static().build());
+
+            Self {
+                bodies,
+                colliders: ColliderSet::new(),
+                joints: JointSet::new(),
+                broad_phase: BroadPhase::new(),
+                narrow_phase: NarrowPhase::new(),
+                point_indices,
+                bounds: Bounds::new().ceiling(true),
+                obstacles: vec![vec![Point2::new(-1.0, 0.0), Point2::new(1.0, 0.5)]],
+            }
+        }
+
+        fn snapshot(&self, frame: u32) -> SnapshotRef<'_> {
+            SnapshotRef {
+                frame,
+                bodies: &self.bodies,
+                colliders: &self.colliders,
+                joints: &self.joints,
+                broad_phase: &self.broad_phase,
+                narrow_phase: &self.narrow_phase,
+                point_indices: &self.point_indices,
+                springs: &[],
+                rigid_glyphs: &[],
+                bounds: &self.bounds,
+                obstacles: &self.obstacles,
+            }
+        }
+
+        fn particle(&self, i: usize) -> Isometry2<f32> {
+            self.bodies.get(self.point_indices[0][i]).unwrap().position
+        }
+    }
+
+    // The poses in the log are single precision, and the angles go through
+    // `UnitComplex::angle`
+    fn assert_near(a: Isometry2<f32>, b: Isometry2<f32>) {
+        let d = a.translation.vector - b.translation.vector;
+        let angle = a.rotation.angle_to(&b.rotation);
+        assert!(d.norm() < 1e-5 && angle.abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let world = World::new();
+        let file = TempFile::new("snapshot.bin");
+        world.snapshot(42).save(&file.0).unwrap();
+
+        let snapshot = Snapshot::load(&file.0).unwrap();
+        assert_eq!(snapshot.frame, 42);
+        assert_eq!(snapshot.bodies.len(), 3);
+        assert_eq!(snapshot.point_indices, world.point_indices);
+        for (i, &handle) in snapshot.point_indices[0].iter().enumerate() {
+            assert_eq!(
+                snapshot.bodies.get(handle).unwrap().position,
+                world.particle(i)
+            );
+        }
+        assert!(snapshot.bounds.ceiling);
+        assert_eq!(snapshot.obstacles, world.obstacles);
+    }
+
+    #[test]
+    fn load_reports_the_path() {
+        let file = TempFile::new("missing.bin");
+        let e = Snapshot::load(&file.0).err().unwrap();
+        assert!(e.contains("missing.bin"), "{}", e);
+    }
+
+    #[test]
+    fn trajectory_replays_the_frames() {
+        let mut world = World::new();
+        let file = TempFile::new("trajectory.bin");
+        let mut writer = TrajectoryWriter::create(&file.0, &world.snapshot(0)).unwrap();
+
+        let mut poses = vec![];
+        for frame in 1..=2 {
+            for (i, &handle) in world.point_indices[0].iter().enumerate() {
+                let v = Vector2::new(i as f32, frame as f32);
+                world.bodies.get_mut(handle).unwrap().position = Isometry2::new(v, 0.5);
+            }
+            writer.write_frame(&world.bodies).unwrap();
+            poses.push((world.particle(0), world.particle(1)));
+        }
+        writer.finish().unwrap();
+
+        // The replay starts from the snapshot taken before the first frame
+        let (mut snapshot, mut trajectory) = Trajectory::load(&file.0).unwrap();
+        assert_eq!(trajectory.n_frames(), 2);
+        let handles = snapshot.point_indices[0].clone();
+        let position = |bodies: &RigidBodySet, i: usize| bodies.get(handles[i]).unwrap().position;
+        assert_eq!(
+            position(&snapshot.bodies, 1),
+            Isometry2::new(Vector2::new(1.0, 1.0), 0.0)
+        );
+
+        for &(pose0, pose1) in &poses {
+            assert!(!trajectory.is_finished());
+            trajectory.next_frame(&mut snapshot.bodies);
+            assert_near(position(&snapshot.bodies, 0), pose0);
+            assert_near(position(&snapshot.bodies, 1), pose1);
+        }
+        assert!(trajectory.is_finished());
+
+        // the bodies stay at the last frame
+        trajectory.next_frame(&mut snapshot.bodies);
+        assert_near(position(&snapshot.bodies, 1), poses[1].1);
+    }
+}
@@ -0,0 +1,298 @@
+use rapier2d::data::arena::Index;
+use rapier2d::dynamics::{BallJoint, JointSet, RigidBodySet};
+use rapier2d::na::Point2;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+use crate::contour::{self, Contour};
+
+/// How to connect two particles.
+#[derive(Clone, Copy, Debug)]
+pub enum Connection {
+    None,
+    /// A rapier ball joint at the middle of the particles. This keeps the
+    /// distance (almost) constant.
+    BallJoint,
+    /// A damped spring that tries to keep the initial distance.
+    Spring {
+        stiffness: f32,
+        damping: f32,
+    },
+}
+
+/// A damped spring between two particles. rapier doesn't provide springs, so
+/// the forces are applied by ourselves before each step.
+#[derive(Serialize, Deserialize)]
+pub struct Spring {
+    pub body1: Index,
+    pub body2: Index,
+    pub rest_length: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+impl Spring {
+    pub fn apply(&self, bodies: &mut RigidBodySet) {
+        let (p1, v1) = match bodies.get(self.body1) {
+            Some(b) => (b.position.translation.vector, b.linvel),
+            None => return,
+        };
+        let (p2, v2) = match bodies.get(self.body2) {
+            Some(b) => (b.position.translation.vector, b.linvel),
+            None => return,
+        };
+
+        let d = p2 - p1;
+        let length = d.norm();
+        if length == 0.0 {
+            return;
+        }
+        let dir = d / length;
+
+        // Hooke's law plus the damping along the spring
+        let magnitude =
+            self.stiffness * (length - self.rest_length) + self.damping * (v2 - v1).dot(&dir);
+        let force = dir * magnitude;
+
+        if let Some(mut b) = bodies.get_mut(self.body1) {
+            b.wake_up(true);
+            b.apply_force(force);
+        }
+        if let Some(mut b) = bodies.get_mut(self.body2) {
+            b.wake_up(true);
+            b.apply_force(-force);
+        }
+    }
+}
+
+/// Connects the particles of a glyph so that it behaves like a soft body.
+///
+/// The neighbouring particles on each contour are connected by `ring`, and
+/// the particles are braced across the inside of the glyph along the edges
+/// of the fill tessellation by `bracing`.
+pub struct SoftBodyBuilder {
+    ring: Connection,
+    bracing: Connection,
+    tolerance: f32,
+}
+
+impl SoftBodyBuilder {
+    pub fn new() -> Self {
+        Self {
+            ring: Connection::BallJoint,
+            bracing: Connection::Spring {
+                stiffness: 50.0,
+                damping: 0.5,
+            },
+            tolerance: 0.01,
+        }
+    }
+
+    pub fn ring(mut self, ring: Connection) -> Self {
+        self.ring = ring;
+        self
+    }
+
+    pub fn bracing(mut self, bracing: Connection) -> Self {
+        self.bracing = bracing;
+        self
+    }
+
+    /// `contours` are the contours of one glyph and `indices` are the
+    /// particles created for each point of them. Joints are inserted into
+    /// `joints` and springs are returned.
+    pub fn build(
+        &self,
+        contours: &[Contour],
+        indices: &[Vec<Index>],
+        bodies: &mut RigidBodySet,
+        joints: &mut JointSet,
+    ) -> Vec<Spring> {
+        let mut springs = vec![];
+        let mut connected = HashSet::new();
+
+        for (contour, indices) in contours.iter().zip(indices) {
+            let n = indices.len();
+            let n_edges = if contour.closed {
+                n
+            } else {
+                n.saturating_sub(1)
+            };
+            for i in 0..n_edges {
+                let (a, b) = (indices[i], indices[(i + 1) % n]);
+                if connected.insert(edge_key(a, b)) {
+                    self.connect(self.ring, a, b, bodies, joints, &mut springs);
+                }
+            }
+        }
+
+        if let Connection::None = self.bracing {
+            return springs;
+        }
+
+        let points: Vec<Point2<f32>> = contours.iter().flat_map(|c| c.points.clone()).collect();
+        let point_indices: Vec<Index> = indices.iter().flatten().cloned().collect();
+
+        let geometry = contour::triangulate(contours, self.tolerance);
+        for triangle in geometry.indices.chunks(3) {
+            // The tessellator can add vertices at self-intersections, so map
+            // each vertex to the nearest particle.
+            let tri: Vec<Index> = triangle
+                .iter()
+                .map(|&i| point_indices[nearest(&points, &geometry.vertices[i as usize])])
+                .collect();
+
+            for i in 0..3 {
+                let (a, b) = (tri[i], tri[(i + 1) % 3]);
+                if a != b && connected.insert(edge_key(a, b)) {
+                    self.connect(self.bracing, a, b, bodies, joints, &mut springs);
+                }
+            }
+        }
+
+        springs
+    }
+
+    fn connect(
+        &self,
+        connection: Connection,
+        a: Index,
+        b: Index,
+        bodies: &mut RigidBodySet,
+        joints: &mut JointSet,
+        springs: &mut Vec<Spring>,
+    ) {
+        let (pos_a, pos_b) = match (bodies.get(a), bodies.get(b)) {
+            (Some(body_a), Some(body_b)) => (body_a.position, body_b.position),
+            _ => return,
+        };
+
+        match connection {
+            Connection::None => {}
+            Connection::BallJoint => {
+                // the anchors are in the local coordinates of each body
+                let mid = Point2::from((pos_a.translation.vector + pos_b.translation.vector) / 2.0);
+                let joint_params = BallJoint::new(
+                    pos_a.inverse_transform_point(&mid),
+                    pos_b.inverse_transform_point(&mid),
+                );
+                joints.insert(bodies, a, b, joint_params);
+            }
+            Connection::Spring { stiffness, damping } => springs.push(Spring {
+                body1: a,
+                body2: b,
+                rest_length: (pos_b.translation.vector - pos_a.translation.vector).norm(),
+                stiffness,
+                damping,
+            }),
+        }
+    }
+}
+
+impl Default for SoftBodyBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn edge_key(a: Index, b: Index) -> (Index, Index) {
+    if a.into_raw_parts() < b.into_raw_parts() {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn nearest(points: &[Point2<f32>], p: &Point2<f32>) -> usize {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, q)| (i, (q - p).norm_squared()))
+        .fold((0, f32::MAX), |acc, x| if x.1 < acc.1 { x } else { acc })
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contour::ContourKind;
+    use rapier2d::dynamics::RigidBodyBuilder;
+    use rapier2d::na::Isometry2;
+
+    const SPRING: Connection = Connection::Spring {
+        stiffness: 10.0,
+        damping: 0.0,
+    };
+
+    // A unit square with a particle at each corner
+    fn square(closed: bool, bodies: &mut RigidBodySet) -> (Contour, Vec<Index>) {
+        let points = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+        let indices = points
+            .iter()
+            .map(|p| {
+                let body = RigidBodyBuilder::new_dynamic()
+                    .position(Isometry2::new(p.coords, 0.0))
+                    .build();
+                bodies.insert(body)
+            })
+            .collect();
+        let contour = Contour {
+            points,
+            closed,
+            path_id: 0,
+            glyph_id: 0,
+            kind: ContourKind::Outer,
+        };
+        (contour, indices)
+    }
+
+    fn build(builder: SoftBodyBuilder, closed: bool) -> (Vec<Spring>, JointSet) {
+        let mut bodies = RigidBodySet::new();
+        let mut joints = JointSet::new();
+        let (contour, indices) = square(closed, &mut bodies);
+        let springs = builder.build(&[contour], &[indices], &mut bodies, &mut joints);
+        (springs, joints)
+    }
+
+    #[test]
+    fn ring_joins_the_neighbours_of_a_closed_contour() {
+        let builder = SoftBodyBuilder::new().bracing(Connection::None);
+        let (springs, joints) = build(builder, true);
+        assert!(springs.is_empty());
+        assert_eq!(joints.len(), 4);
+    }
+
+    #[test]
+    fn ring_of_an_open_contour_has_no_closing_edge() {
+        let builder = SoftBodyBuilder::new()
+            .ring(SPRING)
+            .bracing(Connection::None);
+        let (springs, joints) = build(builder, false);
+        assert_eq!(joints.len(), 0);
+        assert_eq!(springs.len(), 3);
+        for spring in &springs {
+            assert!((spring.rest_length - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn bracing_crosses_the_inside_once() {
+        // The edges of the triangulation along the contour are already
+        // taken by the ring, so only the diagonal is left
+        let builder = SoftBodyBuilder::new()
+            .ring(Connection::None)
+            .bracing(SPRING);
+        let (springs, joints) = build(builder, true);
+        assert_eq!(joints.len(), 0);
+        assert_eq!(springs.len(), 1);
+        assert!((springs[0].rest_length - 2.0f32.sqrt()).abs() < 1e-5);
+        assert_ne!(springs[0].body1, springs[0].body2);
+    }
+}
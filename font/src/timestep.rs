@@ -0,0 +1,183 @@
+use rapier2d::data::arena::Index;
+use rapier2d::dynamics::RigidBodySet;
+use rapier2d::na::{Isometry2, Vector2};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Decides how many physics steps to run in a frame so that the simulation
+/// advances by the real elapsed time in fixed-size steps, regardless of the
+/// frame rate.
+pub struct FixedTimestep {
+    /// The duration of a step in seconds
+    pub dt: f32,
+    /// The number of substeps each step is divided into
+    pub substeps: u32,
+    /// The maximum number of steps in a frame. If the frame took longer
+    /// than this, the simulation slows down instead of trying to catch up
+    /// forever (a.k.a. the spiral of death).
+    pub max_steps: u32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(dt: f32) -> Self {
+        Self {
+            dt,
+            substeps: 1,
+            max_steps: 5,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn substeps(mut self, substeps: u32) -> Self {
+        self.substeps = substeps.max(1);
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// The duration of a substep, which should be set to the integration
+    /// parameters
+    pub fn substep_dt(&self) -> f32 {
+        self.dt / self.substeps as f32
+    }
+
+    /// Adds the elapsed time and returns the number of steps to run.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed.as_secs_f32();
+
+        let mut steps = 0;
+        while self.accumulator >= self.dt && steps < self.max_steps {
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+
+        // drop the time we couldn't catch up with
+        if steps == self.max_steps {
+            self.accumulator = self.accumulator.min(self.dt);
+        }
+
+        steps
+    }
+
+    /// Drops the time that hasn't been stepped yet, so that the next step
+    /// starts from the current time.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+    /// How far the current time is between the last step and the next step,
+    /// from 0.0 to 1.0
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.dt).min(1.0)
+    }
+}
+
+/// Remembers the positions of the bodies before the last step to
+/// interpolate them for rendering.
+#[derive(Default)]
+pub struct Interpolation {
+    prev: HashMap<Index, Isometry2<f32>>,
+}
+
+impl Interpolation {
+    /// This should be called before each step (not substep).
+    pub fn save(&mut self, bodies: &RigidBodySet) {
+        self.prev.clear();
+        for (handle, body) in bodies.iter() {
+            self.prev.insert(handle, body.position);
+        }
+    }
+
+    pub fn position(&self, handle: Index, bodies: &RigidBodySet, alpha: f32) -> Isometry2<f32> {
+        let cur = bodies.get(handle).unwrap().position;
+        let prev = match self.prev.get(&handle) {
+            Some(prev) => prev,
+            None => return cur,
+        };
+
+        let translation: Vector2<f32> =
+            prev.translation.vector.lerp(&cur.translation.vector, alpha);
+        let angle = prev.rotation.angle() + prev.rotation.angle_to(&cur.rotation) * alpha;
+        Isometry2::new(translation, angle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapier2d::dynamics::RigidBodyBuilder;
+
+    #[test]
+    fn steps_follow_the_elapsed_time() {
+        let mut timestep = FixedTimestep::new(0.1);
+        assert_eq!(timestep.advance(Duration::from_millis(250)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+
+        // the leftover carries over to the next frame
+        assert_eq!(timestep.advance(Duration::from_millis(70)), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-4);
+
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 0);
+    }
+
+    #[test]
+    fn max_steps_drops_the_time_behind() {
+        let mut timestep = FixedTimestep::new(0.1).max_steps(3);
+        assert_eq!(timestep.advance(Duration::from_secs(2)), 3);
+        assert_eq!(timestep.alpha(), 1.0);
+        // only one step is left of the rest of the 2 seconds
+        assert_eq!(timestep.advance(Duration::from_secs(0)), 1);
+        assert_eq!(timestep.advance(Duration::from_secs(0)), 0);
+    }
+
+    #[test]
+    fn reset_drops_the_leftover() {
+        let mut timestep = FixedTimestep::new(0.1);
+        assert_eq!(timestep.advance(Duration::from_millis(190)), 1);
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(Duration::from_millis(100)), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn substeps_divide_the_step() {
+        let timestep = FixedTimestep::new(0.1).substeps(4);
+        assert!((timestep.substep_dt() - 0.025).abs() < 1e-6);
+        assert_eq!(FixedTimestep::new(0.1).substeps(0).substeps, 1);
+    }
+
+    #[test]
+    fn interpolation_blends_the_last_two_positions() {
+        let mut bodies = RigidBodySet::new();
+        let handle = bodies.insert(
+            RigidBodyBuilder::new_dynamic()
+                .position(Isometry2::new(Vector2::new(0.0, 0.0), 0.0))
+                .build(),
+        );
+        let mut interpolation = Interpolation::default();
+        interpolation.save(&bodies);
+        bodies.get_mut(handle).unwrap().position = Isometry2::new(Vector2::new(2.0, 4.0), 1.0);
+
+        let p = interpolation.position(handle, &bodies, 0.25);
+        assert!((p.translation.vector - Vector2::new(0.5, 1.0)).norm() < 1e-5);
+        assert!((p.rotation.angle() - 0.25).abs() < 1e-5);
+
+        let p = interpolation.position(handle, &bodies, 1.0);
+        assert!((p.translation.vector - Vector2::new(2.0, 4.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn interpolation_uses_the_current_position_of_new_bodies() {
+        let mut bodies = RigidBodySet::new();
+        let interpolation = Interpolation::default();
+        let position = Isometry2::new(Vector2::new(1.0, 2.0), 0.5);
+        let handle = bodies.insert(RigidBodyBuilder::new_dynamic().position(position).build());
+        assert_eq!(interpolation.position(handle, &bodies, 0.5), position);
+    }
+}
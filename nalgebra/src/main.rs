@@ -1,3 +1,4 @@
+use cpu_render::Input;
 use itertools::iproduct;
 use nannou::prelude::*;
 extern crate nalgebra as na;
@@ -12,9 +13,7 @@ fn model(app: &App) -> Model {
     Model {}
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
-
+fn scene(draw: &cpu_render::Draw, _input: &Input) {
     let cube = iproduct!(0..=1, 0..=1, 0..=1)
         .map(|(x, y, z)| na::Point3::new((x * 100) as f32, (y * 100) as f32, (z * 100) as f32));
 
@@ -32,6 +31,23 @@ fn view(app: &App, model: &Model, frame: Frame) {
             .into();
         draw.ellipse().xy(v.into()).w_h(1.0, 1.0).color(WHITE);
     }
+}
 
+fn view(app: &App, _model: &Model, frame: Frame) {
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, &Input::from_app(app));
+
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden() {
+        cpu_render::assert_scene("nalgebra", &Input::new(1024, 768), scene);
+    }
+}
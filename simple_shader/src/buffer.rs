@@ -0,0 +1,220 @@
+use nannou::prelude::*;
+
+use futures::FutureExt;
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+type Mapping =
+    Pin<Box<dyn Future<Output = Result<wgpu::BufferWriteMapping, wgpu::BufferAsyncErr>>>>;
+
+/// A buffer to upload data through, as wgpu can't write to a buffer in use
+/// directly. It's a ring of buffers so that uploading never waits for the
+/// GPU: a buffer is written again only once its mapping has resolved, i.e.
+/// the GPU has finished the copy from it, and a new buffer is added to the
+/// ring when none is ready. The ring usually settles at the number of frames
+/// in flight.
+pub struct StagingBuffer {
+    // in bytes
+    capacity: wgpu::BufferAddress,
+    ring: RefCell<Ring>,
+}
+
+#[derive(Default)]
+struct Ring {
+    // the buffers waiting to be mapped again
+    pending: Vec<(wgpu::Buffer, Mapping)>,
+    // the buffers copied from by the last `copy()`, whose encoder may not
+    // have been submitted yet
+    used: Vec<wgpu::Buffer>,
+}
+
+impl StagingBuffer {
+    pub fn new(capacity: wgpu::BufferAddress) -> Self {
+        Self {
+            capacity,
+            ring: RefCell::new(Ring::default()),
+        }
+    }
+
+    /// Records the copy of `bytes` to the start of `dst`. `bytes` must fit
+    /// in the capacity.
+    ///
+    /// This should be called at most once per frame, as the buffer used by
+    /// the previous call is mapped again, which can only be requested after
+    /// its encoder has been submitted.
+    pub fn copy(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        bytes: &[u8],
+        dst: &wgpu::Buffer,
+    ) {
+        let size = bytes.len() as wgpu::BufferAddress;
+        assert!(
+            size <= self.capacity,
+            "{} bytes don't fit in {}",
+            size,
+            self.capacity
+        );
+
+        let mut ring = self.ring.borrow_mut();
+        let Ring { pending, used } = &mut *ring;
+        for buffer in used.drain(..) {
+            let future = Box::pin(buffer.map_write(0, self.capacity));
+            pending.push((buffer, future));
+        }
+
+        // Lets the mappings of the finished copies resolve
+        device.poll(wgpu::Maintain::Poll);
+
+        let buffer = match take_mapped(pending) {
+            Some((buffer, mut write)) => {
+                write.as_slice()[..bytes.len()].copy_from_slice(bytes);
+                // unmapped when the mapping is dropped
+                drop(write);
+                buffer
+            }
+            None => {
+                let new = device.create_buffer_mapped(&staging_descriptor(self.capacity));
+                new.data[..bytes.len()].copy_from_slice(bytes);
+                new.finish()
+            }
+        };
+        encoder.copy_buffer_to_buffer(&buffer, 0, dst, 0, size);
+        used.push(buffer);
+    }
+
+    /// Reallocates the buffers if `capacity` is larger than the current one.
+    pub fn reserve(&mut self, capacity: wgpu::BufferAddress) {
+        if capacity > self.capacity {
+            // The buffers in use are kept alive by wgpu until the GPU is done
+            // with them
+            *self.ring.get_mut() = Ring::default();
+            self.capacity = capacity;
+        }
+    }
+}
+
+// Removes the first buffer whose mapping has resolved. The buffers that
+// failed to map are dropped.
+fn take_mapped(
+    pending: &mut Vec<(wgpu::Buffer, Mapping)>,
+) -> Option<(wgpu::Buffer, wgpu::BufferWriteMapping)> {
+    let mut i = 0;
+    while i < pending.len() {
+        match (&mut pending[i].1).now_or_never() {
+            None => i += 1,
+            Some(result) => {
+                let (buffer, _) = pending.remove(i);
+                match result {
+                    Ok(write) => return Some((buffer, write)),
+                    Err(_) => eprintln!("Failed to map a staging buffer"),
+                }
+            }
+        }
+    }
+    None
+}
+
+fn staging_descriptor(size: wgpu::BufferAddress) -> wgpu::BufferDescriptor<'static> {
+    wgpu::BufferDescriptor {
+        label: Some("staging"),
+        size,
+        usage: wgpu::BufferUsage::MAP_WRITE | wgpu::BufferUsage::COPY_SRC,
+    }
+}
+
+/// A GPU buffer holding a slice of `T`. It's kept across frames and
+/// reallocated only when the data outgrows it.
+pub struct GpuBuffer<T> {
+    buffer: wgpu::Buffer,
+    // in bytes
+    capacity: wgpu::BufferAddress,
+    usage: wgpu::BufferUsage,
+    staging: StagingBuffer,
+    _marker: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> GpuBuffer<T> {
+    pub fn new(device: &wgpu::Device, usage: wgpu::BufferUsage, data: &[T]) -> Self {
+        let usage = usage | wgpu::BufferUsage::COPY_DST;
+        let bytes = padded_bytes(data);
+        let capacity = bytes.len() as wgpu::BufferAddress;
+        Self {
+            buffer: device.create_buffer_with_data(&bytes, usage),
+            capacity,
+            usage,
+            staging: StagingBuffer::new(capacity),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Records the copy of `data` into the buffer. If it doesn't fit, the
+    /// buffer is reallocated with some room to grow, so the bind groups
+    /// referring to the buffer must be rebuilt.
+    pub fn write(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, data: &[T]) {
+        let bytes = padded_bytes(data);
+        let size = bytes.len() as wgpu::BufferAddress;
+        if size > self.capacity {
+            self.capacity = size.next_power_of_two();
+            self.buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: self.capacity,
+                usage: self.usage,
+            });
+            self.staging.reserve(self.capacity);
+        }
+
+        self.staging.copy(device, encoder, &bytes, &self.buffer);
+    }
+}
+
+// Copies must be a multiple of 4 bytes (e.g. an odd number of u16 indices
+// isn't), and a buffer can't be empty.
+fn padded_bytes<T: bytemuck::Pod>(data: &[T]) -> Vec<u8> {
+    let mut bytes = bytemuck::cast_slice(data).to_vec();
+    let len = ((bytes.len() + 3) / 4 * 4).max(4);
+    bytes.resize(len, 0);
+    bytes
+}
+
+/// The indices on the GPU. They are stored as `u16` if every vertex can be
+/// addressed with 16 bits, which halves the size, or as `u32` otherwise.
+/// The pipelines drawing them must be built with `format()`.
+pub enum IndexBuffer {
+    U16(GpuBuffer<u16>),
+    U32(GpuBuffer<u32>),
+}
+
+impl IndexBuffer {
+    pub fn new(device: &wgpu::Device, indices: &[u32], n_vertices: usize) -> Self {
+        let usage = wgpu::BufferUsage::INDEX;
+        if n_vertices <= u16::MAX as usize + 1 {
+            let indices: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+            IndexBuffer::U16(GpuBuffer::new(device, usage, &indices))
+        } else {
+            IndexBuffer::U32(GpuBuffer::new(device, usage, indices))
+        }
+    }
+
+    pub fn format(&self) -> wgpu::IndexFormat {
+        match self {
+            IndexBuffer::U16(_) => wgpu::IndexFormat::Uint16,
+            IndexBuffer::U32(_) => wgpu::IndexFormat::Uint32,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.buffer(),
+            IndexBuffer::U32(buffer) => buffer.buffer(),
+        }
+    }
+}
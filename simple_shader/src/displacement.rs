@@ -0,0 +1,221 @@
+use nannou::noise::{Fbm, MultiFractal, NoiseFn, OpenSimplex, Perlin, Seedable, Worley};
+
+// The noise crate doesn't allow more
+const MAX_OCTAVES: usize = 32;
+
+// Where the y component is sampled, far enough from the x component to be
+// independent. The same as in shaders/noise.glsl.
+const Y_OFFSET: [f64; 2] = [31.4, 27.1];
+
+/// The noise functions, which are implemented in both Rust (the noise crate)
+/// and GLSL (shaders/noise.glsl). The two are not identical, but have the
+/// same character and range: the shader hashes the lattice points instead of
+/// using the noise crate's permutation table, so the fields differ and the
+/// seed has no effect on the GPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    Perlin,
+    OpenSimplex,
+    Worley,
+    /// Fractal Brownian motion of Perlin noise
+    Fbm,
+}
+
+impl NoiseKind {
+    // `noise_kind` in the shaders
+    fn id(self) -> u32 {
+        match self {
+            NoiseKind::Perlin => 0,
+            NoiseKind::OpenSimplex => 1,
+            NoiseKind::Worley => 2,
+            NoiseKind::Fbm => 3,
+        }
+    }
+}
+
+/// Where the displacement is computed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Cpu,
+    Gpu,
+}
+
+/// Displaces each vertex by a noise field sampled at its own position and
+/// the time.
+pub struct Displacement {
+    kind: NoiseKind,
+    mode: Mode,
+    amplitude: f32,
+    frequency: f32,
+    speed: f32,
+    octaves: usize,
+    seed: u32,
+    noise: Box<dyn NoiseFn<[f64; 3]>>,
+}
+
+impl Displacement {
+    pub fn new(kind: NoiseKind) -> Self {
+        let mut displacement = Self {
+            kind,
+            mode: Mode::Cpu,
+            amplitude: 0.1,
+            frequency: 1.0,
+            speed: 1.0,
+            octaves: 4,
+            seed: 0,
+            noise: Box::new(Perlin::new()),
+        };
+        displacement.rebuild();
+        displacement
+    }
+
+    /// The maximum distance of the displacement
+    pub fn amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// The higher, the more the neighbouring vertices move differently.
+    pub fn frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// How fast the field changes over time
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// The number of octaves of `NoiseKind::Fbm`
+    pub fn octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves.clamp(1, MAX_OCTAVES);
+        self.rebuild();
+        self
+    }
+
+    /// Used only on the CPU, as the shader's noise isn't seeded
+    pub fn seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self.rebuild();
+        self
+    }
+
+    pub fn set_kind(&mut self, kind: NoiseKind) {
+        self.kind = kind;
+        self.rebuild();
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            Mode::Cpu => Mode::Gpu,
+            Mode::Gpu => Mode::Cpu,
+        };
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The displacement of the vertex at `position` at `time` (in seconds)
+    pub fn offset(&self, position: [f32; 2], time: f32) -> [f32; 2] {
+        let f = self.frequency as f64;
+        let (x, y) = (position[0] as f64 * f, position[1] as f64 * f);
+        let t = (time * self.speed) as f64;
+        let dx = self.noise.get([x, y, t]);
+        let dy = self.noise.get([x + Y_OFFSET[0], y + Y_OFFSET[1], t]);
+        [dx as f32 * self.amplitude, dy as f32 * self.amplitude]
+    }
+
+    /// `displacement` and `noise_kind` of the uniforms. The amplitude is zero
+    /// unless the displacement is computed in the vertex shader.
+    pub fn uniforms(&self) -> ([f32; 4], u32) {
+        let amplitude = match self.mode {
+            Mode::Cpu => 0.0,
+            Mode::Gpu => self.amplitude,
+        };
+        let params = [amplitude, self.frequency, self.speed, self.octaves as f32];
+        (params, self.kind.id())
+    }
+
+    fn rebuild(&mut self) {
+        self.noise = match self.kind {
+            NoiseKind::Perlin => Box::new(Perlin::new().set_seed(self.seed)),
+            NoiseKind::OpenSimplex => Box::new(OpenSimplex::new().set_seed(self.seed)),
+            // the distance to the nearest seed point rather than the value
+            // of the cell, so that it's continuous
+            NoiseKind::Worley => Box::new(Worley::new().set_seed(self.seed).enable_range(true)),
+            NoiseKind::Fbm => Box::new(Fbm::new().set_seed(self.seed).set_octaves(self.octaves)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [NoiseKind; 4] = [
+        NoiseKind::Perlin,
+        NoiseKind::OpenSimplex,
+        NoiseKind::Worley,
+        NoiseKind::Fbm,
+    ];
+
+    // off the lattice, where Perlin noise is always zero
+    const A: [f32; 2] = [0.3, 0.7];
+    const B: [f32; 2] = [1.9, -0.4];
+
+    #[test]
+    fn positions_get_different_offsets() {
+        for &kind in &KINDS {
+            let displacement = Displacement::new(kind).amplitude(0.5);
+            let a = displacement.offset(A, 0.25);
+            let b = displacement.offset(B, 0.25);
+            assert_ne!(a, b, "{:?}", kind);
+            // the x and y components are independent
+            assert_ne!(a[0], a[1], "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn offsets_are_deterministic() {
+        for &kind in &KINDS {
+            let a = Displacement::new(kind).seed(3).offset(A, 0.25);
+            let b = Displacement::new(kind).seed(3).offset(A, 0.25);
+            assert_eq!(a, b, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn offsets_are_scaled_by_the_amplitude() {
+        let small = Displacement::new(NoiseKind::Perlin).amplitude(0.1);
+        let large = Displacement::new(NoiseKind::Perlin).amplitude(0.4);
+        let (small, large) = (small.offset(A, 0.25), large.offset(A, 0.25));
+        for i in 0..2 {
+            assert!((large[i] - small[i] * 4.0).abs() < 1e-6);
+        }
+        let zero = Displacement::new(NoiseKind::Perlin).amplitude(0.0);
+        assert_eq!(zero.offset(A, 0.25), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn seed_changes_the_field() {
+        let a = Displacement::new(NoiseKind::Perlin).seed(1).offset(A, 0.25);
+        let b = Displacement::new(NoiseKind::Perlin).seed(2).offset(A, 0.25);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn time_moves_the_field() {
+        let displacement = Displacement::new(NoiseKind::OpenSimplex);
+        assert_ne!(displacement.offset(A, 0.25), displacement.offset(A, 1.25));
+    }
+
+    #[test]
+    fn only_the_gpu_mode_displaces_in_the_shader() {
+        let mut displacement = Displacement::new(NoiseKind::Worley).amplitude(0.5);
+        assert_eq!(displacement.uniforms(), ([0.0, 1.0, 1.0, 4.0], 2));
+        displacement.toggle_mode();
+        assert_eq!(displacement.uniforms(), ([0.5, 1.0, 1.0, 4.0], 2));
+    }
+}
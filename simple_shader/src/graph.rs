@@ -0,0 +1,285 @@
+use nannou::prelude::*;
+
+// The bind group of the input textures. Set 0 is left for the bind group
+// shared by all the pipelines.
+pub const INPUT_SET: u32 = 1;
+
+/// Where a pass renders to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+    Texture(&'static str),
+    Frame,
+}
+
+struct TargetDesc {
+    name: &'static str,
+    sample_count: u32,
+}
+
+struct PassDesc {
+    name: &'static str,
+    inputs: Vec<&'static str>,
+    output: Output,
+}
+
+/// Declares the textures and the passes of a `RenderGraph`. The passes are
+/// run in the order they are added.
+pub struct RenderGraphBuilder {
+    frame_samples: u32,
+    targets: Vec<TargetDesc>,
+    passes: Vec<PassDesc>,
+}
+
+impl RenderGraphBuilder {
+    /// `frame_samples` is the MSAA sample count of the frame.
+    pub fn new(frame_samples: u32) -> Self {
+        Self {
+            frame_samples,
+            targets: vec![],
+            passes: vec![],
+        }
+    }
+
+    /// An intermediate texture of the same size as the frame. A multisampled
+    /// texture is resolved at the end of the pass that renders to it.
+    pub fn texture(mut self, name: &'static str, sample_count: u32) -> Self {
+        self.targets.push(TargetDesc { name, sample_count });
+        self
+    }
+
+    /// In the shaders, the inputs are bound at `INPUT_SET`: a sampler at
+    /// binding 0 followed by the textures in the same order as `inputs`.
+    pub fn pass(mut self, name: &'static str, inputs: &[&'static str], output: Output) -> Self {
+        self.passes.push(PassDesc {
+            name,
+            inputs: inputs.to_vec(),
+            output,
+        });
+        self
+    }
+
+    pub fn build(self, device: &wgpu::Device, size: [u32; 2]) -> Result<RenderGraph, String> {
+        let target_index = |name: &str| {
+            self.targets
+                .iter()
+                .position(|t| t.name == name)
+                .ok_or_else(|| format!("no such texture: {}", name))
+        };
+
+        let mut passes = Vec::with_capacity(self.passes.len());
+        for desc in &self.passes {
+            let inputs = desc
+                .inputs
+                .iter()
+                .map(|name| target_index(name))
+                .collect::<Result<Vec<_>, _>>()?;
+            let output = match desc.output {
+                Output::Texture(name) => Some(target_index(name)?),
+                Output::Frame => None,
+            };
+            if output.map_or(false, |i| inputs.contains(&i)) {
+                return Err(format!("{} reads the texture it renders to", desc.name));
+            }
+
+            let mut layout =
+                wgpu::BindGroupLayoutBuilder::new().sampler(wgpu::ShaderStage::FRAGMENT);
+            for _ in &inputs {
+                layout = layout.sampled_texture(
+                    wgpu::ShaderStage::FRAGMENT,
+                    false,
+                    wgpu::TextureViewDimension::D2,
+                    wgpu::TextureComponentType::Float,
+                );
+            }
+
+            passes.push(Pass {
+                name: desc.name,
+                inputs,
+                output,
+                layout: layout.build(device),
+                bind_group: None,
+            });
+        }
+
+        let sampler = wgpu::SamplerBuilder::new()
+            .mag_filter(wgpu::FilterMode::Linear)
+            .min_filter(wgpu::FilterMode::Linear)
+            .build(device);
+
+        let mut graph = RenderGraph {
+            size: [0, 0],
+            frame_samples: self.frame_samples,
+            sampler,
+            targets: self
+                .targets
+                .into_iter()
+                .map(|desc| Target { desc, views: None })
+                .collect(),
+            passes,
+        };
+        graph.resize(device, size);
+        Ok(graph)
+    }
+}
+
+struct TargetViews {
+    // kept alive for the views
+    _textures: Vec<wgpu::Texture>,
+    // the texture rendered to if multisampled
+    msaa: Option<wgpu::TextureView>,
+    // the texture sampled by the later passes
+    resolved: wgpu::TextureView,
+}
+
+struct Target {
+    desc: TargetDesc,
+    // None until the first resize
+    views: Option<TargetViews>,
+}
+
+struct Pass {
+    name: &'static str,
+    // indices of the targets
+    inputs: Vec<usize>,
+    output: Option<usize>,
+    layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+/// A chain of render passes, where each pass renders to either an
+/// intermediate texture or the frame, and samples the textures rendered by
+/// the earlier passes. The textures, the sampler and the bind groups are
+/// created by the graph.
+pub struct RenderGraph {
+    size: [u32; 2],
+    frame_samples: u32,
+    sampler: wgpu::Sampler,
+    targets: Vec<Target>,
+    passes: Vec<Pass>,
+}
+
+impl RenderGraph {
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Recreates the textures and the bind groups if the size has changed.
+    pub fn resize(&mut self, device: &wgpu::Device, size: [u32; 2]) {
+        if size == self.size {
+            return;
+        }
+        self.size = size;
+
+        for target in &mut self.targets {
+            target.views = Some(create_views(device, size, target.desc.sample_count));
+        }
+
+        for pass in &mut self.passes {
+            let mut builder = wgpu::BindGroupBuilder::new().sampler(&self.sampler);
+            for &i in &pass.inputs {
+                let views = self.targets[i].views.as_ref().unwrap();
+                builder = builder.texture_view(&views.resolved);
+            }
+            pass.bind_group = Some(builder.build(device, &pass.layout));
+        }
+    }
+
+    /// The view to sample the texture through, e.g. to post-process it
+    /// outside of the graph
+    pub fn texture_view(&self, name: &str) -> &wgpu::TextureView {
+        let target = self
+            .targets
+            .iter()
+            .find(|target| target.desc.name == name)
+            .unwrap_or_else(|| panic!("no such texture: {}", name));
+        &target.views.as_ref().unwrap().resolved
+    }
+
+    /// The layout of the inputs of the pass, to build its pipelines with
+    pub fn input_layout(&self, pass: &str) -> &wgpu::BindGroupLayout {
+        &self.pass(pass).layout
+    }
+
+    /// The sample count the pipelines of the pass need
+    pub fn sample_count(&self, pass: &str) -> u32 {
+        match self.pass(pass).output {
+            Some(i) => self.targets[i].desc.sample_count,
+            None => self.frame_samples,
+        }
+    }
+
+    /// Begins the pass with its output cleared and its inputs bound.
+    pub fn begin<'a>(
+        &'a self,
+        pass: &str,
+        encoder: &'a mut wgpu::CommandEncoder,
+        frame_view: &'a wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
+        let pass = self.pass(pass);
+        let (attachment, resolve_target) = match pass.output {
+            Some(i) => {
+                let views = self.targets[i].views.as_ref().unwrap();
+                match &views.msaa {
+                    Some(msaa) => (msaa, Some(&views.resolved)),
+                    None => (&views.resolved, None),
+                }
+            }
+            None => (frame_view, None),
+        };
+
+        let mut render_pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(attachment, |color| {
+                color
+                    .resolve_target(resolve_target)
+                    .clear_color(wgpu::Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 1.0,
+                    })
+            })
+            .begin(encoder);
+        render_pass.set_bind_group(INPUT_SET, pass.bind_group.as_ref().unwrap(), &[]);
+        render_pass
+    }
+
+    fn pass(&self, name: &str) -> &Pass {
+        self.passes
+            .iter()
+            .find(|pass| pass.name == name)
+            .unwrap_or_else(|| panic!("no such pass: {}", name))
+    }
+}
+
+fn create_views(device: &wgpu::Device, size: [u32; 2], sample_count: u32) -> TargetViews {
+    let builder = || {
+        wgpu::TextureBuilder::new()
+            .size(size)
+            .format(Frame::TEXTURE_FORMAT)
+    };
+    let mut textures = vec![];
+
+    let msaa = if sample_count > 1 {
+        let texture = builder()
+            .sample_count(sample_count)
+            .usage(wgpu::TextureUsage::OUTPUT_ATTACHMENT)
+            .build(device);
+        let view = texture.create_default_view();
+        textures.push(texture);
+        Some(view)
+    } else {
+        None
+    };
+
+    let texture = builder()
+        .usage(wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED)
+        .build(device);
+    let resolved = texture.create_default_view();
+    textures.push(texture);
+
+    TargetViews {
+        _textures: textures,
+        msaa,
+        resolved,
+    }
+}
@@ -1,22 +1,77 @@
-use lyon::math::point;
+use bloom::{Bloom, BloomSettings};
+use lyon::algorithms::aabb::bounding_rect;
+use lyon::math::{point, Rect};
 use lyon::path::Path;
 use lyon::tessellation;
 use lyon::tessellation::geometry_builder::*;
 use lyon::tessellation::{FillOptions, FillTessellator};
 use lyon::tessellation::{StrokeOptions, StrokeTessellator};
 use nannou::prelude::*;
+use svg_path::{Artwork, Segment};
 
 use std::ops::Range;
-
-use nannou::noise::*;
+use std::path::{Path as FilePath, PathBuf};
+
+mod buffer;
+mod displacement;
+mod graph;
+mod material;
+mod paint;
+mod shader;
+mod uniforms;
+use buffer::{GpuBuffer, IndexBuffer};
+use displacement::{Displacement, Mode, NoiseKind};
+use graph::{Output, RenderGraph, RenderGraphBuilder};
+use material::{BlendMode, DrawRange, Material, MaterialId, Materials, MATERIAL_SET};
+use paint::{Gradient, Paint, Shading};
+use shader::{ShaderCompiler, ShaderWatcher};
+use uniforms::{UniformBuffer, Uniforms};
+
+// The outline that is drawn unless an SVG file is given
+const PATH_DATA: &str = "M-0.8 -0.3 Q1.5 2.3 0.2 -0.9";
+
+// The passes and the textures of the render graph
+const SCENE: &str = "scene";
+const BLUR_H: &str = "blur_h";
+const BLUR_V: &str = "blur_v";
+const BLUR: &str = "blur";
+const COMPOSITE: &str = "composite";
+
+// The length of the mip chain of the bloom
+const BLOOM_LEVELS: usize = 5;
 
 struct Model {
+    // shared by all the pipelines at set 0
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
-    render_pipeline: wgpu::RenderPipeline,
-    blur_render_pipeline: wgpu::RenderPipeline,
-    geometry: VertexBuffers<Vertex, u16>,
-    fill_count: Count,
-    noise: Perlin,
+    uniform_buffer: UniformBuffer,
+    // passed to the shaders as `params`
+    params: [f32; 4],
+    graph: RenderGraph,
+    pipelines: Pipelines,
+    glow: Glow,
+    bloom: Bloom,
+    shader_dir: PathBuf,
+    shader_compiler: ShaderCompiler,
+    // None if the directories can't be watched; the shaders are still
+    // compiled once
+    shader_watcher: Option<ShaderWatcher>,
+    // The last compile error, which is shown on the window until the shaders
+    // are fixed. Meanwhile, the last good pipelines are used.
+    shader_error: Option<String>,
+    // u32 indices so that large tessellations don't overflow
+    geometry: VertexBuffers<Vertex, u32>,
+    // the copies of `geometry` on the GPU
+    vertex_buffer: GpuBuffer<Vertex>,
+    index_buffer: IndexBuffer,
+    materials: Materials,
+    // the ranges of `geometry` to draw, in order
+    draws: Vec<DrawRange>,
+    fill: Fill,
+    // set when the vertices are changed outside of `update`
+    vertices_dirty: bool,
+    // moves each vertex by a noise field sampled at its position
+    displacement: Displacement,
 }
 
 // The vertex type that we will use to represent a point on our triangle.
@@ -24,34 +79,98 @@ struct Model {
 #[derive(Clone, Copy)]
 struct Vertex {
     position: [f32; 2],
+    // the displacement computed on the CPU, or zero
     noise: [f32; 2],
+    // from the paint of the shape
+    color: [f32; 4],
+    uv: [f32; 2],
 }
 
 unsafe impl bytemuck::Pod for Vertex {}
 unsafe impl bytemuck::Zeroable for Vertex {}
 
+// The paints the fill cycles through, each with the material to draw it
+// with
+struct Fill {
+    paints: Vec<(Paint, MaterialId)>,
+    current: usize,
+    // the vertices of the fill in `geometry`
+    vertices: Range<usize>,
+    bounds: Rect,
+}
+
+// How the glow is added to the scene on the way to the frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Glow {
+    // the blur and the composite passes of the render graph, which are
+    // tuned by `params`
+    Blur,
+    Bloom,
+}
+
+struct Pipelines {
+    scene_alpha: wgpu::RenderPipeline,
+    scene_additive: wgpu::RenderPipeline,
+    blur_h: wgpu::RenderPipeline,
+    blur_v: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+}
+
+impl Pipelines {
+    // The scene pipeline for the blend mode of a material
+    fn scene(&self, blend: BlendMode) -> &wgpu::RenderPipeline {
+        match blend {
+            BlendMode::Alpha => &self.scene_alpha,
+            BlendMode::Additive => &self.scene_additive,
+        }
+    }
+}
+
 fn main() {
     nannou::app(model).update(update).run();
 }
 
 fn model(app: &App) -> Model {
-    let mut noise = Perlin::new();
-    noise = noise.set_seed(1);
-
-    let w_id = app.new_window().size(512, 512).view(view).build().unwrap();
+    let w_id = app
+        .new_window()
+        .size(512, 512)
+        .key_pressed(key_pressed)
+        .view(view)
+        .build()
+        .unwrap();
 
     // The gpu device associated with the window's swapchain
     let window = app.window(w_id).unwrap();
     let device = window.swap_chain_device();
 
-    // Build a Path.
-    let mut builder = Path::builder();
-    builder.begin(point(-0.8, -0.3));
-    builder.quadratic_bezier_to(point(1.5, 2.3), point(0.2, -0.9));
-    builder.end(false);
-    let path = builder.build();
+    // Draw the shapes in an SVG file instead, and fill them with an image
+    // as one of the paints, e.g.:
+    //
+    //     cargo run --release -- artwork.svg --texture image.png
+    let mut artwork_path = None;
+    let mut texture_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--texture" => texture_path = args.next(),
+            _ => artwork_path = Some(arg),
+        }
+    }
 
-    let mut geometry: VertexBuffers<Vertex, u16> = VertexBuffers::new();
+    let segments = match artwork_path {
+        // in clip space, with a margin
+        Some(path) => match Artwork::load(&path).and_then(|a| a.fit([0.0, 0.0], [1.6, 1.6])) {
+            Ok(paths) => paths.concat(),
+            Err(e) => {
+                eprintln!("Failed to load the SVG: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => svg_path::parse_path_data(PATH_DATA).unwrap(),
+    };
+    let path = to_path(&segments);
+
+    let mut geometry: VertexBuffers<Vertex, u32> = VertexBuffers::new();
 
     let tolerance = 0.0001;
 
@@ -60,9 +179,8 @@ fn model(app: &App) -> Model {
         .tessellate_path(
             &path,
             &FillOptions::tolerance(tolerance).with_fill_rule(tessellation::FillRule::NonZero),
-            &mut BuffersBuilder::new(&mut geometry, |vertex: tessellation::FillVertex| Vertex {
-                position: vertex.position().to_array(),
-                noise: [0.0, 0.0],
+            &mut BuffersBuilder::new(&mut geometry, |vertex: tessellation::FillVertex| {
+                new_vertex(vertex.position().to_array())
             }),
         )
         .unwrap();
@@ -72,135 +190,599 @@ fn model(app: &App) -> Model {
         .tessellate_path(
             &path,
             &StrokeOptions::tolerance(tolerance).with_line_width(0.13),
-            &mut BuffersBuilder::new(&mut geometry, |vertex: tessellation::StrokeVertex| Vertex {
-                position: vertex.position().to_array(),
-                noise: [0.0, 0.0],
+            &mut BuffersBuilder::new(&mut geometry, |vertex: tessellation::StrokeVertex| {
+                new_vertex(vertex.position().to_array())
             }),
         )
         .unwrap();
 
+    // Fill the path with a translucent paint and stroke it on top. The
+    // colours of the fill are in the vertices, so its materials are white.
+    // The stroke is added to what's below it, which makes it glow where it
+    // crosses the fill.
+    let mut materials = Materials::new(&window);
+    let plain = materials.add(device, Material::new(WHITE, BlendMode::Alpha));
+    let stroke = materials.add(
+        device,
+        Material::new(rgba(1.0, 0.85, 0.9, 0.7), BlendMode::Additive),
+    );
+    let mut paints = vec![
+        (Paint::solid(rgba(0.3, 0.4, 1.0, 0.35)), plain),
+        (
+            Paint::gradient(
+                Gradient::Linear { angle: 0.0 },
+                &[
+                    (0.0, rgba(0.3, 0.4, 1.0, 0.6)),
+                    (1.0, rgba(1.0, 0.3, 0.5, 0.6)),
+                ],
+            ),
+            plain,
+        ),
+        (
+            Paint::gradient(
+                Gradient::Radial,
+                &[
+                    (0.0, rgba(1.0, 1.0, 1.0, 0.8)),
+                    (1.0, rgba(0.3, 0.4, 1.0, 0.1)),
+                ],
+            ),
+            plain,
+        ),
+        (
+            Paint::gradient(
+                Gradient::Conic { angle: 0.0 },
+                &[
+                    (0.0, rgba(1.0, 0.2, 0.2, 0.5)),
+                    (1.0 / 3.0, rgba(0.2, 1.0, 0.2, 0.5)),
+                    (2.0 / 3.0, rgba(0.2, 0.2, 1.0, 0.5)),
+                    (1.0, rgba(1.0, 0.2, 0.2, 0.5)),
+                ],
+            ),
+            plain,
+        ),
+    ];
+    if let Some(path) = texture_path {
+        match wgpu::Texture::from_path(&*window, &path) {
+            Ok(texture) => {
+                let material = Material::new(rgba(1.0, 1.0, 1.0, 0.8), BlendMode::Alpha);
+                let textured = materials.add_textured(device, material, texture);
+                paints.push((Paint::Texture, textured));
+            }
+            Err(e) => {
+                eprintln!("Failed to load {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let fill = Fill {
+        paints,
+        current: 0,
+        vertices: 0..fill_count.vertices as usize,
+        bounds: bounding_rect(path.iter()),
+    };
+    let (paint, fill_material) = &fill.paints[fill.current];
+    shade(
+        &mut geometry.vertices[fill.vertices.clone()],
+        paint,
+        fill.bounds,
+    );
+    shade(
+        &mut geometry.vertices[fill.vertices.end..],
+        &Paint::solid(WHITE),
+        fill.bounds,
+    );
+
+    let fill_end = fill_count.indices;
+    let draws = vec![
+        DrawRange {
+            indices: 0..fill_end,
+            material: *fill_material,
+        },
+        DrawRange {
+            indices: fill_end..geometry.indices.len() as u32,
+            material: stroke,
+        },
+    ];
+
+    let vertex_buffer = GpuBuffer::new(device, wgpu::BufferUsage::VERTEX, &geometry.vertices);
+    let index_buffer = IndexBuffer::new(device, &geometry.indices, geometry.vertices.len());
+
     // Create the render pipeline.
-    let bind_group_layout = wgpu::BindGroupLayoutBuilder::new().build(device);
-    let bind_group = wgpu::BindGroupBuilder::new().build(device, &bind_group_layout);
-    let pipeline_layout = wgpu::create_pipeline_layout(device, &[&bind_group_layout]);
-
-    // Load shader modules.
-    let vs_mod = wgpu::shader_from_spirv_bytes(device, include_bytes!("shaders/shader.vert.spv"));
-    let fs_mod = wgpu::shader_from_spirv_bytes(device, include_bytes!("shaders/shader.frag.spv"));
-    let render_pipeline = wgpu::RenderPipelineBuilder::from_layout(&pipeline_layout, &vs_mod)
-        .fragment_shader(&fs_mod)
-        .color_format(Frame::TEXTURE_FORMAT)
-        .add_vertex_buffer::<Vertex>(&wgpu::vertex_attr_array![0 => Float2])
-        .index_format(wgpu::IndexFormat::Uint16)
-        .sample_count(window.msaa_samples())
-        .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+    let uniform_buffer = UniformBuffer::new(device);
+    let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+        .uniform_buffer(
+            wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            false,
+        )
         .build(device);
+    let bind_group = wgpu::BindGroupBuilder::new()
+        .buffer::<Uniforms>(uniform_buffer.buffer(), 0..1)
+        .build(device, &bind_group_layout);
+
+    // Draw the scene into a texture, blur it horizontally and then
+    // vertically, and add the blurred one to the original as a glow. The
+    // bloom reads the same texture instead of the blur passes.
+    let msaa_samples = window.msaa_samples();
+    let (width, height) = window.inner_size_pixels();
+    let graph = RenderGraphBuilder::new(msaa_samples)
+        .texture(SCENE, msaa_samples)
+        .texture(BLUR_H, 1)
+        .texture(BLUR, 1)
+        .pass(SCENE, &[], Output::Texture(SCENE))
+        .pass(BLUR_H, &[SCENE], Output::Texture(BLUR_H))
+        .pass(BLUR_V, &[BLUR_H], Output::Texture(BLUR))
+        .pass(COMPOSITE, &[SCENE, BLUR], Output::Frame)
+        .build(device, [width, height])
+        .unwrap();
 
-    let vs_mod_blur =
-        wgpu::shader_from_spirv_bytes(device, include_bytes!("shaders/blur.vert.spv"));
-    let fs_mod_blur =
-        wgpu::shader_from_spirv_bytes(device, include_bytes!("shaders/blur.frag.spv"));
-    let blur_render_pipeline =
-        wgpu::RenderPipelineBuilder::from_layout(&pipeline_layout, &vs_mod_blur)
-            .fragment_shader(&fs_mod_blur)
-            .color_format(Frame::TEXTURE_FORMAT)
-            .add_vertex_buffer::<Vertex>(&wgpu::vertex_attr_array![0 => Float2, 1 => Float2])
-            .index_format(wgpu::IndexFormat::Uint16)
-            .sample_count(window.msaa_samples())
-            .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
-            .color_blend(wgpu::BlendDescriptor {
-                src_factor: wgpu::BlendFactor::One,
-                dst_factor: wgpu::BlendFactor::One,
-                operation: wgpu::BlendOperation::Add,
-            })
-            .build(device);
+    // The shaders are compiled at runtime and rebuilt whenever they are
+    // saved. Edit src/shaders/*.{vert,frag}, or the shaders of the bloom
+    // crate, while the sketch is running.
+    let shader_dir = app
+        .project_path()
+        .expect("failed to locate `project_path`")
+        .join("src")
+        .join("shaders");
+    let mut shader_compiler = ShaderCompiler::new();
+    let pipelines = match build_pipelines(
+        device,
+        &bind_group_layout,
+        materials.layout(),
+        index_buffer.format(),
+        &graph,
+        &mut shader_compiler,
+        &shader_dir,
+    ) {
+        Ok(pipelines) => pipelines,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let bloom = match Bloom::new(
+        device,
+        [width, height],
+        BLOOM_LEVELS,
+        msaa_samples,
+        |path, defines| shader_compiler.compile(path, defines),
+    ) {
+        Ok(bloom) => bloom.settings(BloomSettings {
+            intensity: 1.5,
+            ..BloomSettings::default()
+        }),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let shader_dirs = [shader_dir.as_path(), FilePath::new(bloom::SHADER_DIR)];
+    let shader_watcher = match ShaderWatcher::new(&shader_dirs) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            eprintln!("Shaders won't be reloaded: {}", e);
+            None
+        }
+    };
 
     Model {
+        bind_group_layout,
         bind_group,
-        render_pipeline,
-        blur_render_pipeline,
+        uniform_buffer,
+        // the intensity of the glow and the spread of the blur
+        params: [1.5, 2.0, 0.0, 0.0],
+        graph,
+        pipelines,
+        glow: Glow::Bloom,
+        bloom,
+        shader_dir,
+        shader_compiler,
+        shader_watcher,
+        shader_error: None,
         geometry,
-        fill_count,
-        noise,
+        vertex_buffer,
+        index_buffer,
+        materials,
+        draws,
+        fill,
+        vertices_dirty: false,
+        displacement: Displacement::new(NoiseKind::Perlin)
+            .amplitude(0.1)
+            .frequency(1.5)
+            .speed(0.3)
+            .octaves(4)
+            .seed(1),
     }
 }
 
-fn update(app: &App, model: &mut Model, _: Update) {
-    for vertex in model.geometry.vertices.iter_mut() {
-        let cur = app.elapsed_frames() as f32 / 4000.0;
-        let cur = model
-            .noise
-            .get([-1.0, 1.0, cur.sin() as f64, cur.cos() as f64])
-            * 10.0;
-        vertex.noise = [cur.sin() as f32 * 0.17, cur.cos() as f32 * 0.12];
+fn new_vertex(position: [f32; 2]) -> Vertex {
+    Vertex {
+        position,
+        noise: [0.0, 0.0],
+        color: [1.0; 4],
+        uv: [0.0, 0.0],
     }
 }
 
-// Draw the state of your `Model` into the given `Frame` here.
-fn view(_app: &App, model: &Model, frame: Frame) {
-    let device = frame.device_queue_pair().device();
+// Computes the colours and the texture coordinates of the vertices of a
+// shape with the bounding box `bounds`
+fn shade(vertices: &mut [Vertex], paint: &Paint, bounds: Rect) {
+    let shading = Shading::new(paint, bounds);
+    for vertex in vertices {
+        let position = point(vertex.position[0], vertex.position[1]);
+        vertex.color = shading.color(position);
+        vertex.uv = shading.uv(position);
+    }
+}
 
-    let fill_range = 0..model.fill_count.indices;
-    let stroke_range = fill_range.end..(model.geometry.indices.len() as u32);
+fn to_path(segments: &[Segment]) -> Path {
+    let p = |p: [f32; 2]| point(p[0], p[1]);
+    let mut builder = Path::builder();
+    // lyon needs every subpath to be ended explicitly
+    let mut open = false;
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo(to) => {
+                if open {
+                    builder.end(false);
+                }
+                builder.begin(p(to));
+                open = true;
+            }
+            Segment::LineTo(to) => {
+                builder.line_to(p(to));
+            }
+            Segment::QuadraticTo { ctrl, to } => {
+                builder.quadratic_bezier_to(p(ctrl), p(to));
+            }
+            Segment::CubicTo { ctrl1, ctrl2, to } => {
+                builder.cubic_bezier_to(p(ctrl1), p(ctrl2), p(to));
+            }
+            Segment::Close => {
+                builder.end(true);
+                open = false;
+            }
+        }
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
 
-    let vertex_buffer = device.create_buffer_with_data(
-        bytemuck::cast_slice(&model.geometry.vertices),
-        wgpu::BufferUsage::VERTEX,
+fn build_pipelines(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    material_layout: &wgpu::BindGroupLayout,
+    index_format: wgpu::IndexFormat,
+    graph: &RenderGraph,
+    compiler: &mut ShaderCompiler,
+    shader_dir: &FilePath,
+) -> Result<Pipelines, String> {
+    let mut load = |name: &str, defines: &[&str]| -> Result<wgpu::ShaderModule, String> {
+        let spirv = compiler.compile(&shader_dir.join(name), defines)?;
+        Ok(wgpu::shader_from_spirv_bytes(device, &spirv))
+    };
+
+    // Load shader modules. Compile all of them before building anything so
+    // that every error is reported before the first one is fixed.
+    let vs_mod = load("shader.vert", &[]);
+    let fs_mod = load("shader.frag", &[]);
+    let vs_mod_fullscreen = load("fullscreen.vert", &[]);
+    let fs_mod_blur_h = load("blur.frag", &["HORIZONTAL"]);
+    let fs_mod_blur_v = load("blur.frag", &[]);
+    let fs_mod_composite = load("composite.frag", &[]);
+    let errors: Vec<String> = [
+        &vs_mod,
+        &fs_mod,
+        &vs_mod_fullscreen,
+        &fs_mod_blur_h,
+        &fs_mod_blur_v,
+        &fs_mod_composite,
+    ]
+    .iter()
+    .filter_map(|result| result.as_ref().err().cloned())
+    .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let (vs_mod, fs_mod, vs_mod_fullscreen) = (vs_mod?, fs_mod?, vs_mod_fullscreen?);
+    let (fs_mod_blur_h, fs_mod_blur_v) = (fs_mod_blur_h?, fs_mod_blur_v?);
+    let fs_mod_composite = fs_mod_composite?;
+
+    // The shared bind group at set 0 and the inputs of the pass at set 1
+    let pipeline_layout = |pass: &str| {
+        wgpu::create_pipeline_layout(device, &[bind_group_layout, graph.input_layout(pass)])
+    };
+
+    // The scene pass also has the material at set 2, and a pipeline per
+    // blend mode
+    let scene_layout = wgpu::create_pipeline_layout(
+        device,
+        &[
+            bind_group_layout,
+            graph.input_layout(SCENE),
+            material_layout,
+        ],
     );
+    let scene = |blend: BlendMode| {
+        wgpu::RenderPipelineBuilder::from_layout(&scene_layout, &vs_mod)
+            .fragment_shader(&fs_mod)
+            .color_format(Frame::TEXTURE_FORMAT)
+            .color_blend(blend.descriptor())
+            .alpha_blend(blend.descriptor())
+            .add_vertex_buffer::<Vertex>(
+                &wgpu::vertex_attr_array![0 => Float2, 1 => Float2, 2 => Float4, 3 => Float2],
+            )
+            .index_format(index_format)
+            .sample_count(graph.sample_count(SCENE))
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+            .build(device)
+    };
 
-    let index_buffer = device.create_buffer_with_data(
-        bytemuck::cast_slice(&model.geometry.indices),
-        wgpu::BufferUsage::INDEX,
+    let fullscreen = |pass: &str, fs_mod: &wgpu::ShaderModule| {
+        wgpu::RenderPipelineBuilder::from_layout(&pipeline_layout(pass), &vs_mod_fullscreen)
+            .fragment_shader(fs_mod)
+            .color_format(Frame::TEXTURE_FORMAT)
+            .sample_count(graph.sample_count(pass))
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+            .build(device)
+    };
+
+    Ok(Pipelines {
+        scene_alpha: scene(BlendMode::Alpha),
+        scene_additive: scene(BlendMode::Additive),
+        blur_h: fullscreen(BLUR_H, &fs_mod_blur_h),
+        blur_v: fullscreen(BLUR_V, &fs_mod_blur_v),
+        composite: fullscreen(COMPOSITE, &fs_mod_composite),
+    })
+}
+
+// Rebuilds the pipelines, or keeps the current ones if the shaders don't
+// compile.
+fn reload_shaders(app: &App, model: &mut Model) {
+    let window = app.main_window();
+    let device = window.swap_chain_device();
+    let pipelines = build_pipelines(
+        device,
+        &model.bind_group_layout,
+        model.materials.layout(),
+        model.index_buffer.format(),
+        &model.graph,
+        &mut model.shader_compiler,
+        &model.shader_dir,
     );
+    let compiler = &mut model.shader_compiler;
+    let bloom = model
+        .bloom
+        .reload(device, |path, defines| compiler.compile(path, defines));
+
+    let mut errors = vec![];
+    match pipelines {
+        Ok(pipelines) => model.pipelines = pipelines,
+        Err(e) => errors.push(e),
+    }
+    if let Err(e) = bloom {
+        errors.push(e);
+    }
+    if errors.is_empty() {
+        println!("Reloaded the shaders");
+        model.shader_error = None;
+    } else {
+        let e = errors.join("\n");
+        eprintln!("{}", e);
+        model.shader_error = Some(e);
+    }
+}
+
+fn update(app: &App, model: &mut Model, _: Update) {
+    let shaders_changed = model
+        .shader_watcher
+        .as_ref()
+        .map_or(false, |watcher| watcher.changed());
+    if shaders_changed {
+        reload_shaders(app, model);
+    }
+
+    // The intermediate textures follow the size of the frame
+    {
+        let window = app.main_window();
+        let (width, height) = window.inner_size_pixels();
+        let device = window.swap_chain_device();
+        model.graph.resize(device, [width, height]);
+        model.bloom.resize(device, [width, height]);
+    }
 
-    let mut staging_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-        label: Some("encoder 2"),
+    let vertices = &mut model.geometry.vertices;
+    let changed = match model.displacement.mode() {
+        Mode::Cpu => {
+            for vertex in vertices.iter_mut() {
+                vertex.noise = model.displacement.offset(vertex.position, app.time);
+            }
+            true
+        }
+        // The vertex shader displaces the vertices, so only the offsets left
+        // by the CPU need clearing
+        Mode::Gpu => {
+            let dirty = vertices.iter().any(|vertex| vertex.noise != [0.0, 0.0]);
+            for vertex in vertices.iter_mut() {
+                vertex.noise = [0.0, 0.0];
+            }
+            dirty
+        }
+    };
+    let changed = changed || std::mem::replace(&mut model.vertices_dirty, false);
+    if !changed {
+        return;
+    }
+
+    // Only the vertices have changed; the indices stay on the GPU as they are
+    let window = app.main_window();
+    let device_queue_pair = window.swap_chain_device_queue_pair();
+    let device = device_queue_pair.device();
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("upload vertices"),
     });
+    model
+        .vertex_buffer
+        .write(device, &mut encoder, &model.geometry.vertices);
+    device_queue_pair
+        .queue()
+        .lock()
+        .unwrap()
+        .submit(&[encoder.finish()]);
+}
 
-    let frame_texture_view = frame.texture_view();
-    let staging_texture = wgpu::TextureBuilder::new()
-        .size(frame_texture_view.size())
-        .build(device);
-    let staging_texture_view = staging_texture.create_default_view();
-
-    let mut render_pass = wgpu::RenderPassBuilder::new()
-        .color_attachment(&staging_texture_view, |color| {
-            color.clear_color(wgpu::Color {
-                r: 0.0,
-                g: 0.0,
-                b: 0.0,
-                a: 1.0,
-            })
-        })
-        .begin(&mut staging_encoder);
+fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+    match key {
+        // Select the noise function
+        Key::Key1 => model.displacement.set_kind(NoiseKind::Perlin),
+        Key::Key2 => model.displacement.set_kind(NoiseKind::OpenSimplex),
+        Key::Key3 => model.displacement.set_kind(NoiseKind::Worley),
+        Key::Key4 => model.displacement.set_kind(NoiseKind::Fbm),
+        // Displace the vertices on the CPU or in the vertex shader. The
+        // shader has its own noise functions, which look alike but aren't
+        // the same field, so the shapes jump when switching.
+        Key::G => {
+            model.displacement.toggle_mode();
+            match model.displacement.mode() {
+                Mode::Cpu => println!("Displacing on the CPU"),
+                Mode::Gpu => println!(
+                    "Displacing on the GPU, with noise that differs from the CPU's and ignores the seed"
+                ),
+            }
+        }
+        // Cycle through the paints of the fill
+        Key::P => {
+            let fill = &mut model.fill;
+            fill.current = (fill.current + 1) % fill.paints.len();
+            let (paint, material) = &fill.paints[fill.current];
+            shade(
+                &mut model.geometry.vertices[fill.vertices.clone()],
+                paint,
+                fill.bounds,
+            );
+            model.draws[0].material = *material;
+            model.vertices_dirty = true;
+        }
+        // Switch between the blur and the bloom
+        Key::B => {
+            model.glow = match model.glow {
+                Glow::Blur => Glow::Bloom,
+                Glow::Bloom => Glow::Blur,
+            };
+            println!("Glowing with the {:?}", model.glow);
+        }
+        // Adjust the intensity and the spread of the glow
+        Key::Up | Key::Down | Key::Left | Key::Right => match model.glow {
+            Glow::Blur => {
+                let [intensity, spread, ..] = &mut model.params;
+                match key {
+                    Key::Up => *intensity += 0.1,
+                    Key::Down => *intensity = (*intensity - 0.1).max(0.0),
+                    Key::Right => *spread += 0.25,
+                    _ => *spread = (*spread - 0.25).max(0.0),
+                }
+                println!("Blur intensity: {:.1}, spread: {:.2}", intensity, spread);
+            }
+            Glow::Bloom => {
+                let settings = &mut model.bloom.settings;
+                match key {
+                    Key::Up => settings.intensity += 0.1,
+                    Key::Down => settings.intensity = (settings.intensity - 0.1).max(0.0),
+                    Key::Right => settings.radius += 0.25,
+                    _ => settings.radius = (settings.radius - 0.25).max(0.0),
+                }
+                println!(
+                    "Bloom intensity: {:.1}, radius: {:.2}",
+                    settings.intensity, settings.radius
+                );
+            }
+        },
+        _ => {}
+    }
+}
+
+// Draw the state of your `Model` into the given `Frame` here.
+fn view(app: &App, model: &Model, frame: Frame) {
+    let [width, height] = frame.texture_view().size();
+    let win = app.window_rect();
+    let (displacement, noise_kind) = model.displacement.uniforms();
+    let uniforms = Uniforms {
+        params: model.params,
+        displacement,
+        resolution: [width as f32, height as f32],
+        mouse: [app.mouse.x / win.w() * 2.0, app.mouse.y / win.h() * 2.0],
+        time: app.time,
+        frame: app.elapsed_frames() as u32,
+        noise_kind,
+    };
+    encode_passes(model, &frame, &uniforms);
+
+    // Show the compile error on top of the output of the last good shaders
+    if let Some(error) = &model.shader_error {
+        let win = win.pad(20.0);
+        let draw = app.draw();
+        draw.text(error)
+            .xy(win.xy())
+            .wh(win.wh())
+            .font_size(14)
+            .left_justify()
+            .align_text_top()
+            .color(RED);
+        draw.to_frame(app, &frame).unwrap();
+    }
+}
+
+fn encode_passes(model: &Model, frame: &Frame, uniforms: &Uniforms) {
+    let device = frame.device_queue_pair().device();
 
+    let frame_view = frame.texture_view();
     let mut encoder = frame.command_encoder();
-    let mut render_pass_blur = wgpu::RenderPassBuilder::new()
-        .color_attachment(frame_texture_view, |color| {
-            color.clear_color(wgpu::Color {
-                r: 0.0,
-                g: 0.0,
-                b: 0.0,
-                a: 1.0,
-            })
-        })
-        .begin(&mut encoder);
-
-    render_pass.set_bind_group(0, &model.bind_group, &[]);
-
-    render_pass.set_pipeline(&model.render_pipeline);
-    render_pass.set_index_buffer(&index_buffer, 0, 0);
-    render_pass.set_vertex_buffer(0, &vertex_buffer, 0, 0);
-
-    // render_pass.draw_indexed(model.fill_range.clone(), 0, 0..1);
-    render_pass.draw_indexed(stroke_range.clone(), 0, 0..1);
-
-    render_pass_blur.set_bind_group(0, &model.bind_group, &[]);
-
-    render_pass_blur.set_pipeline(&model.blur_render_pipeline);
-    render_pass_blur.set_index_buffer(&index_buffer, 0, 0);
-    render_pass_blur.set_vertex_buffer(0, &vertex_buffer, 0, 0);
-
-    // render_pass.draw_indexed(model.fill_range.clone(), 0, 0..1);
-    render_pass_blur.draw_indexed(stroke_range.clone(), 0, 0..1);
+
+    model.uniform_buffer.upload(device, &mut encoder, uniforms);
+
+    {
+        let mut render_pass = model.graph.begin(SCENE, &mut encoder, frame_view);
+        render_pass.set_bind_group(0, &model.bind_group, &[]);
+
+        render_pass.set_index_buffer(model.index_buffer.buffer(), 0, 0);
+        render_pass.set_vertex_buffer(0, model.vertex_buffer.buffer(), 0, 0);
+
+        for draw in &model.draws {
+            let material = model.materials.get(draw.material);
+            render_pass.set_pipeline(model.pipelines.scene(material.blend));
+            render_pass.set_bind_group(
+                MATERIAL_SET,
+                model.materials.bind_group(draw.material),
+                &[],
+            );
+            render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+        }
+    }
+
+    match model.glow {
+        // The rest of the passes sample the output of the earlier ones
+        Glow::Blur => {
+            let fullscreen_passes = [
+                (BLUR_H, &model.pipelines.blur_h),
+                (BLUR_V, &model.pipelines.blur_v),
+                (COMPOSITE, &model.pipelines.composite),
+            ];
+            for &(pass, pipeline) in fullscreen_passes.iter() {
+                let mut render_pass = model.graph.begin(pass, &mut encoder, frame_view);
+                render_pass.set_bind_group(0, &model.bind_group, &[]);
+                render_pass.set_pipeline(pipeline);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+        Glow::Bloom => model.bloom.encode(
+            device,
+            &mut encoder,
+            model.graph.texture_view(SCENE),
+            frame_view,
+        ),
+    }
 }
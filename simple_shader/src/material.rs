@@ -0,0 +1,170 @@
+use nannou::color::IntoLinSrgba;
+use nannou::image;
+use nannou::prelude::*;
+
+use std::ops::Range;
+
+// The bind group of the material, after the shared one and the inputs of
+// the pass
+pub const MATERIAL_SET: u32 = 2;
+
+/// How a material is blended with what's drawn before it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Alpha,
+    Additive,
+}
+
+impl BlendMode {
+    pub fn descriptor(self) -> wgpu::BlendDescriptor {
+        let dst_factor = match self {
+            BlendMode::Alpha => wgpu::BlendFactor::OneMinusSrcAlpha,
+            BlendMode::Additive => wgpu::BlendFactor::One,
+        };
+        wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Material {
+    pub color: LinSrgba,
+    pub blend: BlendMode,
+}
+
+impl Material {
+    pub fn new<C: IntoLinSrgba<f32>>(color: C, blend: BlendMode) -> Self {
+        Self {
+            color: color.into_lin_srgba(),
+            blend,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaterialId(usize);
+
+/// A range of the indices drawn with a material
+#[derive(Clone, Debug)]
+pub struct DrawRange {
+    pub indices: Range<u32>,
+    pub material: MaterialId,
+}
+
+// The uniform block `Material` in shader.frag
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MaterialUniforms {
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for MaterialUniforms {}
+unsafe impl bytemuck::Zeroable for MaterialUniforms {}
+
+struct Entry {
+    material: Material,
+    // kept alive for the bind group
+    _buffer: wgpu::Buffer,
+    _texture: Option<wgpu::Texture>,
+    bind_group: wgpu::BindGroup,
+}
+
+/// The materials and their bind groups
+pub struct Materials {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // bound to the materials without a texture, so that the shader is the
+    // same for all
+    white: wgpu::Texture,
+    entries: Vec<Entry>,
+}
+
+impl Materials {
+    pub fn new(window: &Window) -> Self {
+        let device = window.swap_chain_device();
+        let layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStage::FRAGMENT, false)
+            .sampler(wgpu::ShaderStage::FRAGMENT)
+            .sampled_texture(
+                wgpu::ShaderStage::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                wgpu::TextureComponentType::Float,
+            )
+            .build(device);
+        let sampler = wgpu::SamplerBuilder::new()
+            .mag_filter(wgpu::FilterMode::Linear)
+            .min_filter(wgpu::FilterMode::Linear)
+            .build(device);
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let white = wgpu::Texture::from_image(window, &image::DynamicImage::ImageRgba8(white));
+        Self {
+            layout,
+            sampler,
+            white,
+            entries: vec![],
+        }
+    }
+
+    /// The layout of the bind group at `MATERIAL_SET`
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn add(&mut self, device: &wgpu::Device, material: Material) -> MaterialId {
+        self.push(device, material, None)
+    }
+
+    /// A material whose colour is multiplied by `texture`, which is sampled
+    /// at the texture coordinates of the vertices.
+    pub fn add_textured(
+        &mut self,
+        device: &wgpu::Device,
+        material: Material,
+        texture: wgpu::Texture,
+    ) -> MaterialId {
+        self.push(device, material, Some(texture))
+    }
+
+    fn push(
+        &mut self,
+        device: &wgpu::Device,
+        material: Material,
+        texture: Option<wgpu::Texture>,
+    ) -> MaterialId {
+        let (r, g, b, a) = material.color.into_components();
+        let uniforms = MaterialUniforms {
+            color: [r, g, b, a],
+        };
+        let buffer = device
+            .create_buffer_with_data(bytemuck::bytes_of(&uniforms), wgpu::BufferUsage::UNIFORM);
+        let view = texture
+            .as_ref()
+            .unwrap_or(&self.white)
+            .create_default_view();
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .buffer::<MaterialUniforms>(&buffer, 0..1)
+            .sampler(&self.sampler)
+            .texture_view(&view)
+            .build(device, &self.layout);
+
+        self.entries.push(Entry {
+            material,
+            _buffer: buffer,
+            _texture: texture,
+            bind_group,
+        });
+        MaterialId(self.entries.len() - 1)
+    }
+
+    pub fn get(&self, id: MaterialId) -> &Material {
+        &self.entries[id.0].material
+    }
+
+    pub fn bind_group(&self, id: MaterialId) -> &wgpu::BindGroup {
+        &self.entries[id.0].bind_group
+    }
+}
@@ -0,0 +1,286 @@
+use lyon::math::{Point, Rect};
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+
+use std::f32::consts::PI;
+
+/// The shape of a gradient over the bounding box of a path
+#[derive(Clone, Copy, Debug)]
+pub enum Gradient {
+    /// From one side of the box to the other, in the direction of `angle`
+    /// (in radians, 0.0 is left to right)
+    Linear { angle: f32 },
+    /// From the centre of the box to the ellipse inscribed in it
+    Radial,
+    /// Around the centre of the box, starting at `angle`
+    Conic { angle: f32 },
+}
+
+/// How the vertices of a shape are coloured. The colours are computed per
+/// vertex when the shape is tessellated and interpolated in between, so the
+/// gradients other than the two-stop linear ones are only as smooth as the
+/// tessellation is fine.
+#[derive(Clone, Debug)]
+pub enum Paint {
+    Solid(LinSrgba),
+    /// The stops are positions from 0.0 to 1.0 with their colours, in order
+    Gradient(Gradient, Vec<(f32, LinSrgba)>),
+    /// The texture of the material, stretched over the bounding box
+    Texture,
+}
+
+impl Paint {
+    pub fn solid<C: IntoLinSrgba<f32>>(color: C) -> Self {
+        Paint::Solid(color.into_lin_srgba())
+    }
+
+    pub fn gradient<C: IntoLinSrgba<f32> + Copy>(gradient: Gradient, stops: &[(f32, C)]) -> Self {
+        let stops = stops
+            .iter()
+            .map(|&(t, color)| (t, color.into_lin_srgba()))
+            .collect();
+        Paint::Gradient(gradient, stops)
+    }
+}
+
+/// Computes the colour and the texture coordinates of the vertices of a
+/// shape from their positions.
+pub struct Shading<'a> {
+    paint: &'a Paint,
+    bounds: Rect,
+}
+
+impl<'a> Shading<'a> {
+    /// `bounds` is the bounding box of the shape.
+    pub fn new(paint: &'a Paint, bounds: Rect) -> Self {
+        Self { paint, bounds }
+    }
+
+    pub fn color(&self, position: Point) -> [f32; 4] {
+        let color = match self.paint {
+            Paint::Solid(color) => *color,
+            Paint::Gradient(gradient, stops) => {
+                // relative to the centre of the box, which is 1.0 wide
+                let [u, v] = self.normalize(position);
+                let (x, y) = (u - 0.5, v - 0.5);
+                let t = match *gradient {
+                    Gradient::Linear { angle } => {
+                        let (sin, cos) = angle.sin_cos();
+                        // the projection of the corners is ±0.5 at most
+                        let half = 0.5 * (cos.abs() + sin.abs());
+                        (x * cos + y * sin) / half * 0.5 + 0.5
+                    }
+                    Gradient::Radial => (x * x + y * y).sqrt() * 2.0,
+                    Gradient::Conic { angle } => {
+                        ((y.atan2(x) - angle) / (2.0 * PI)).rem_euclid(1.0)
+                    }
+                };
+                sample(stops, t)
+            }
+            Paint::Texture => LinSrgba::new(1.0, 1.0, 1.0, 1.0),
+        };
+        let (r, g, b, a) = color.into_components();
+        [r, g, b, a]
+    }
+
+    /// The bounding box maps to the whole texture.
+    pub fn uv(&self, position: Point) -> [f32; 2] {
+        let [u, v] = self.normalize(position);
+        // the v axis of textures points down
+        [u, 1.0 - v]
+    }
+
+    // From 0.0 to 1.0 in the box, with the y axis pointing up
+    fn normalize(&self, position: Point) -> [f32; 2] {
+        let (origin, size) = (self.bounds.origin, self.bounds.size);
+        let w = size.width.max(f32::EPSILON);
+        let h = size.height.max(f32::EPSILON);
+        [(position.x - origin.x) / w, (position.y - origin.y) / h]
+    }
+}
+
+// The colour at `t` between the stops
+fn sample(stops: &[(f32, LinSrgba)], t: f32) -> LinSrgba {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return LinSrgba::new(0.0, 0.0, 0.0, 0.0),
+    };
+    if t <= first.0 {
+        return first.1;
+    }
+    for pair in stops.windows(2) {
+        let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+        if t <= t1 {
+            let s = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+            return mix(c0, c1, s);
+        }
+    }
+    last.1
+}
+
+fn mix(a: LinSrgba, b: LinSrgba, s: f32) -> LinSrgba {
+    let (ar, ag, ab, aa) = a.into_components();
+    let (br, bg, bb, ba) = b.into_components();
+    LinSrgba::new(
+        ar + (br - ar) * s,
+        ag + (bg - ag) * s,
+        ab + (bb - ab) * s,
+        aa + (ba - aa) * s,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon::math::{point, rect};
+
+    fn black() -> LinSrgba {
+        LinSrgba::new(0.0, 0.0, 0.0, 1.0)
+    }
+
+    fn white() -> LinSrgba {
+        LinSrgba::new(1.0, 1.0, 1.0, 1.0)
+    }
+
+    fn red() -> LinSrgba {
+        LinSrgba::new(1.0, 0.0, 0.0, 1.0)
+    }
+
+    fn blue() -> LinSrgba {
+        LinSrgba::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    fn assert_color(actual: LinSrgba, expected: LinSrgba) {
+        let (actual, expected) = (actual.into_components(), expected.into_components());
+        let diff = [
+            actual.0 - expected.0,
+            actual.1 - expected.1,
+            actual.2 - expected.2,
+            actual.3 - expected.3,
+        ];
+        assert!(
+            diff.iter().all(|d| d.abs() < 1e-4),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    // The position of the gradient from 0.0 to 1.0 at the points of a box
+    // 4 wide and 2 high, given as fractions of the box from the bottom left
+    // corner, as the red of a gradient from black to white
+    fn positions(gradient: Gradient, points: &[[f32; 2]]) -> Vec<f32> {
+        let paint = Paint::gradient(gradient, &[(0.0, black()), (1.0, white())]);
+        let shading = Shading::new(&paint, rect(10.0, 20.0, 4.0, 2.0));
+        points
+            .iter()
+            .map(|&[u, v]| shading.color(point(10.0 + u * 4.0, 20.0 + v * 2.0))[0])
+            .collect()
+    }
+
+    fn assert_positions(gradient: Gradient, points: &[[f32; 2]], expected: &[f32]) {
+        let actual = positions(gradient, points);
+        assert!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| (a - e).abs() < 1e-4),
+            "{:?}: {:?} != {:?}",
+            gradient,
+            actual,
+            expected
+        );
+    }
+
+    // bottom left, bottom right, top right, top left, and the centre
+    const CORNERS_AND_CENTRE: [[f32; 2]; 5] =
+        [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.5, 0.5]];
+
+    // the middles of the right, top, left and bottom edges
+    const EDGES: [[f32; 2]; 4] = [[1.0, 0.5], [0.5, 1.0], [0.0, 0.5], [0.5, 0.0]];
+
+    #[test]
+    fn linear() {
+        // left to right
+        assert_positions(
+            Gradient::Linear { angle: 0.0 },
+            &CORNERS_AND_CENTRE,
+            &[0.0, 1.0, 1.0, 0.0, 0.5],
+        );
+        // bottom to top
+        assert_positions(
+            Gradient::Linear { angle: PI / 2.0 },
+            &CORNERS_AND_CENTRE,
+            &[0.0, 0.0, 1.0, 1.0, 0.5],
+        );
+        // diagonally, from corner to corner
+        assert_positions(
+            Gradient::Linear { angle: PI / 4.0 },
+            &CORNERS_AND_CENTRE,
+            &[0.0, 0.5, 1.0, 0.5, 0.5],
+        );
+    }
+
+    #[test]
+    fn radial() {
+        // the corners are outside the ellipse, so they get the last stop
+        assert_positions(
+            Gradient::Radial,
+            &CORNERS_AND_CENTRE,
+            &[1.0, 1.0, 1.0, 1.0, 0.0],
+        );
+        assert_positions(Gradient::Radial, &EDGES, &[1.0, 1.0, 1.0, 1.0]);
+        assert_positions(Gradient::Radial, &[[0.75, 0.5]], &[0.5]);
+    }
+
+    #[test]
+    fn conic() {
+        // counter-clockwise from the right
+        assert_positions(
+            Gradient::Conic { angle: 0.0 },
+            &EDGES,
+            &[0.0, 0.25, 0.5, 0.75],
+        );
+        assert_positions(
+            Gradient::Conic { angle: 0.0 },
+            &CORNERS_AND_CENTRE,
+            &[0.625, 0.875, 0.125, 0.375, 0.0],
+        );
+        // from the top
+        assert_positions(
+            Gradient::Conic { angle: PI / 2.0 },
+            &EDGES,
+            &[0.75, 0.0, 0.25, 0.5],
+        );
+    }
+
+    #[test]
+    fn stops_are_clamped() {
+        let stops = [(0.25, red()), (0.75, blue())];
+        assert_color(sample(&stops, -1.0), red());
+        assert_color(sample(&stops, 0.0), red());
+        assert_color(sample(&stops, 0.5), LinSrgba::new(0.5, 0.0, 0.5, 1.0));
+        assert_color(sample(&stops, 1.0), blue());
+        assert_color(sample(&stops, 2.0), blue());
+    }
+
+    #[test]
+    fn equal_stops_make_a_hard_edge() {
+        let stops = [(0.0, black()), (0.5, red()), (0.5, blue()), (1.0, white())];
+        assert_color(sample(&stops, 0.5 - 1e-6), red());
+        assert_color(sample(&stops, 0.5), red());
+        assert_color(sample(&stops, 0.5 + 1e-6), blue());
+
+        // a single stop, or all the stops at the same position
+        assert_color(sample(&[(0.5, red())], 0.7), red());
+        let stops = [(0.5, red()), (0.5, blue())];
+        assert_color(sample(&stops, 0.2), red());
+        assert_color(sample(&stops, 0.5), red());
+        assert_color(sample(&stops, 0.7), blue());
+    }
+
+    #[test]
+    fn no_stops_are_transparent() {
+        assert_color(sample(&[], 0.5), LinSrgba::new(0.0, 0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,115 @@
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+// How long to wait for the editor to finish writing a file
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Compiles GLSL shaders into SPIR-V at runtime, so that no stale `.spv`
+/// file is ever used.
+pub struct ShaderCompiler {
+    compiler: shaderc::Compiler,
+}
+
+impl ShaderCompiler {
+    pub fn new() -> Self {
+        Self {
+            compiler: shaderc::Compiler::new().expect("failed to initialize shaderc"),
+        }
+    }
+
+    /// The kind of the shader is decided by the extension, `.vert` or
+    /// `.frag`. `#include "..."` is resolved relative to the shader, and
+    /// `defines` are defined as macros so that one file can be compiled into
+    /// variants. The error contains the messages of the compiler.
+    pub fn compile(&mut self, path: &Path, defines: &[&str]) -> Result<Vec<u8>, String> {
+        let file_name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+
+        let kind = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("vert") => shaderc::ShaderKind::Vertex,
+            Some("frag") => shaderc::ShaderKind::Fragment,
+            _ => return Err(format!("{}: unknown kind of shader", file_name)),
+        };
+
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+        let mut options =
+            shaderc::CompileOptions::new().expect("failed to initialize shaderc options");
+        for name in defines {
+            options.add_macro_definition(name, None);
+        }
+        let dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+        options.set_include_callback(move |name, _, _, _| {
+            let path = dir.join(name);
+            match std::fs::read_to_string(&path) {
+                Ok(content) => Ok(shaderc::ResolvedInclude {
+                    resolved_name: path.display().to_string(),
+                    content,
+                }),
+                Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+            }
+        });
+
+        let artifact = self
+            .compiler
+            .compile_into_spirv(&source, kind, &file_name, "main", Some(&options))
+            .map_err(|e| format!("{}: {}", file_name, e))?;
+        Ok(artifact.as_binary_u8().to_vec())
+    }
+}
+
+/// Watches directories for changes of shader files.
+pub struct ShaderWatcher {
+    // the watcher stops when dropped
+    _watcher: RecommendedWatcher,
+    rx: Receiver<DebouncedEvent>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dirs: &[&Path]) -> Result<Self, String> {
+        let (tx, rx) = channel();
+        let mut watcher = notify::watcher(tx, DEBOUNCE).map_err(|e| e.to_string())?;
+        for dir in dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|e| format!("failed to watch {}: {}", dir.display(), e))?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            rx,
+        })
+    }
+
+    /// Returns true if any shader has changed since the last call. This
+    /// doesn't block.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.rx.try_iter() {
+            let path = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => path,
+                _ => continue,
+            };
+            changed |= is_shader(&path);
+        }
+        changed
+    }
+}
+
+fn is_shader(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("vert") | Some("frag") | Some("glsl")
+    )
+}
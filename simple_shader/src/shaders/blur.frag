@@ -1,7 +1,35 @@
 #version 450
+#extension GL_GOOGLE_include_directive : require
+
+// One direction of a separable Gaussian blur. Compiled with HORIZONTAL
+// defined for the horizontal pass.
+
+layout(location = 0) in vec2 uv;
 
 layout(location = 0) out vec4 f_color;
 
+#include "uniforms.glsl"
+
+layout(set = 1, binding = 0) uniform sampler s_input;
+layout(set = 1, binding = 1) uniform texture2D t_input;
+
+// 9 taps in 5 fetches, using the linear filtering between the texels
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
 void main() {
-    f_color = vec4(0.5, 0.5, 1.0, 0.7);
+#ifdef HORIZONTAL
+    vec2 direction = vec2(1.0, 0.0);
+#else
+    vec2 direction = vec2(0.0, 1.0);
+#endif
+    // params.y spreads the taps to widen the blur
+    vec2 texel_step = params.y * direction / vec2(textureSize(sampler2D(t_input, s_input), 0));
+
+    vec4 color = texture(sampler2D(t_input, s_input), uv) * WEIGHTS[0];
+    for (int i = 1; i < 3; i++) {
+        color += texture(sampler2D(t_input, s_input), uv + texel_step * OFFSETS[i]) * WEIGHTS[i];
+        color += texture(sampler2D(t_input, s_input), uv - texel_step * OFFSETS[i]) * WEIGHTS[i];
+    }
+    f_color = color;
 }
@@ -0,0 +1,23 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+// Adds the blurred scene on top of the scene as a glow.
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 f_color;
+
+#include "uniforms.glsl"
+
+layout(set = 1, binding = 0) uniform sampler s_input;
+layout(set = 1, binding = 1) uniform texture2D t_scene;
+layout(set = 1, binding = 2) uniform texture2D t_blur;
+
+const vec3 GLOW_COLOR = vec3(0.5, 0.5, 1.0);
+
+void main() {
+    vec3 scene = texture(sampler2D(t_scene, s_input), uv).rgb;
+    vec3 blur = texture(sampler2D(t_blur, s_input), uv).rgb;
+    // params.x is the intensity of the glow
+    f_color = vec4(scene + blur * GLOW_COLOR * params.x, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 450
+
+// A triangle covering the whole frame, drawn with `draw(0..3, 0..1)`
+
+layout(location = 0) out vec2 uv;
+
+void main() {
+    vec2 p = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    // the v axis of textures points down
+    uv = vec2(p.x, 1.0 - p.y);
+    gl_Position = vec4(p * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,127 @@
+// Noise functions for displacing the vertices, the counterparts of the ones
+// in src/displacement.rs. They aren't the same as the noise crate's, but
+// have the same character and range, roughly -1.0 to 1.0. The lattice
+// points are hashed rather than looked up in a seeded permutation table, so
+// the seed of the CPU side has no counterpart here. Needs uniforms.glsl.
+
+#define NOISE_PERLIN 0u
+#define NOISE_OPEN_SIMPLEX 1u
+#define NOISE_WORLEY 2u
+#define NOISE_FBM 3u
+
+// The same as the noise crate's Fbm
+const float LACUNARITY = 2.0943951;
+const float PERSISTENCE = 0.5;
+const int MAX_OCTAVES = 32;
+
+// Where the y component is sampled, the same as `Y_OFFSET` in Rust
+const vec2 Y_OFFSET = vec2(31.4, 27.1);
+
+// A pseudo-random vector from -1.0 to 1.0 per lattice point
+vec3 hash3(vec3 p) {
+    p = vec3(dot(p, vec3(127.1, 311.7, 74.7)),
+             dot(p, vec3(269.5, 183.3, 246.1)),
+             dot(p, vec3(113.5, 271.9, 124.6)));
+    return fract(sin(p) * 43758.5453123) * 2.0 - 1.0;
+}
+
+float grad(vec3 i, vec3 f, vec3 corner) {
+    return dot(hash3(i + corner), f - corner);
+}
+
+float perlin(vec3 p) {
+    vec3 i = floor(p);
+    vec3 f = fract(p);
+    // the quintic curve, as in improved Perlin noise
+    vec3 u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    float x00 = mix(grad(i, f, vec3(0, 0, 0)), grad(i, f, vec3(1, 0, 0)), u.x);
+    float x10 = mix(grad(i, f, vec3(0, 1, 0)), grad(i, f, vec3(1, 1, 0)), u.x);
+    float x01 = mix(grad(i, f, vec3(0, 0, 1)), grad(i, f, vec3(1, 0, 1)), u.x);
+    float x11 = mix(grad(i, f, vec3(0, 1, 1)), grad(i, f, vec3(1, 1, 1)), u.x);
+    return mix(mix(x00, x10, u.y), mix(x01, x11, u.y), u.z);
+}
+
+// Simplex noise, which has fewer directional artifacts than Perlin noise
+// like OpenSimplex
+float simplex(vec3 p) {
+    const float F3 = 1.0 / 3.0;
+    const float G3 = 1.0 / 6.0;
+
+    // the corners of the simplex containing p
+    vec3 i = floor(p + dot(p, vec3(F3)));
+    vec3 x0 = p - i + dot(i, vec3(G3));
+    vec3 g = step(x0.yzx, x0.xyz);
+    vec3 l = 1.0 - g;
+    vec3 i1 = min(g, l.zxy);
+    vec3 i2 = max(g, l.zxy);
+
+    vec3 x1 = x0 - i1 + G3;
+    vec3 x2 = x0 - i2 + 2.0 * G3;
+    vec3 x3 = x0 - 1.0 + 3.0 * G3;
+
+    vec4 w = max(0.6 - vec4(dot(x0, x0), dot(x1, x1), dot(x2, x2), dot(x3, x3)), 0.0);
+    w *= w;
+    w *= w;
+    vec4 d = vec4(dot(hash3(i), x0),
+                  dot(hash3(i + i1), x1),
+                  dot(hash3(i + i2), x2),
+                  dot(hash3(i + 1.0), x3));
+    return 32.0 * dot(w, d);
+}
+
+// The distance to the nearest feature point, one per cell
+float worley(vec3 p) {
+    vec3 i = floor(p);
+    vec3 f = fract(p);
+
+    float nearest = 8.0;
+    for (int z = -1; z <= 1; z++) {
+        for (int y = -1; y <= 1; y++) {
+            for (int x = -1; x <= 1; x++) {
+                vec3 cell = vec3(x, y, z);
+                vec3 feature = cell + 0.5 + 0.5 * hash3(i + cell);
+                vec3 r = feature - f;
+                nearest = min(nearest, dot(r, r));
+            }
+        }
+    }
+    return sqrt(nearest) * 2.0 - 1.0;
+}
+
+float fbm(vec3 p, int octaves) {
+    float sum = 0.0;
+    float amplitude = 1.0;
+    float total = 0.0;
+    for (int octave = 0; octave < MAX_OCTAVES; octave++) {
+        if (octave >= octaves) {
+            break;
+        }
+        sum += perlin(p) * amplitude;
+        total += amplitude;
+        p *= LACUNARITY;
+        amplitude *= PERSISTENCE;
+    }
+    return sum / total;
+}
+
+float noise(vec3 p) {
+    switch (noise_kind) {
+    case NOISE_OPEN_SIMPLEX:
+        return simplex(p);
+    case NOISE_WORLEY:
+        return worley(p);
+    case NOISE_FBM:
+        return fbm(p, int(displacement.w));
+    default:
+        return perlin(p);
+    }
+}
+
+// The offset of the vertex at `position`, sampled at its own position and
+// the time
+vec2 displace(vec2 position) {
+    vec3 p = vec3(position * displacement.y, time * displacement.z);
+    vec3 q = p + vec3(Y_OFFSET, 0.0);
+    return displacement.x * vec2(noise(p), noise(q));
+}
@@ -1,7 +1,18 @@
 #version 450
 
+layout(location = 0) in vec4 v_color;
+layout(location = 1) in vec2 v_uv;
+
 layout(location = 0) out vec4 f_color;
 
+// the colour and the texture of the material, which are set per range of
+// the geometry. The texture is white unless the material has one.
+layout(set = 2, binding = 0) uniform Material {
+    vec4 color;
+};
+layout(set = 2, binding = 1) uniform sampler s_material;
+layout(set = 2, binding = 2) uniform texture2D t_material;
+
 void main() {
-    f_color = vec4(1.0, 1.0, 1.0, 1.0);
-}
\ No newline at end of file
+    f_color = v_color * color * texture(sampler2D(t_material, s_material), v_uv);
+}
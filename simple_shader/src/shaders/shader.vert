@@ -1,8 +1,26 @@
-
 #version 450
+#extension GL_GOOGLE_include_directive : require
 
 layout(location = 0) in vec2 position;
+// the displacement computed on the CPU, or zero
+layout(location = 1) in vec2 noise_offset;
+// the paint of the shape, computed when it's tessellated
+layout(location = 2) in vec4 color;
+layout(location = 3) in vec2 uv;
+
+layout(location = 0) out vec4 v_color;
+layout(location = 1) out vec2 v_uv;
+
+#include "uniforms.glsl"
+#include "noise.glsl"
 
 void main() {
-    gl_Position = vec4(position, 0.0, 1.0);
-}
\ No newline at end of file
+    vec2 p = position + noise_offset;
+    // The amplitude is zero unless the displacement is done here
+    if (displacement.x != 0.0) {
+        p += displace(position);
+    }
+    gl_Position = vec4(p, 0.0, 1.0);
+    v_color = color;
+    v_uv = uv;
+}
@@ -0,0 +1,18 @@
+// The uniforms shared by all the shaders. Keep this in sync with `Uniforms`
+// in src/uniforms.rs.
+layout(set = 0, binding = 0) uniform Uniforms {
+    // free parameters of the sketch
+    vec4 params;
+    // the amplitude, the frequency, the speed and the octaves of the noise
+    // displacement, or zero amplitude if it's done on the CPU
+    vec4 displacement;
+    // the size of the frame in pixels
+    vec2 resolution;
+    // the mouse position in the same coordinates as the vertices
+    vec2 mouse;
+    // in seconds
+    float time;
+    uint frame;
+    // see noise.glsl
+    uint noise_kind;
+};
@@ -0,0 +1,66 @@
+use nannou::prelude::*;
+
+use crate::buffer::StagingBuffer;
+
+/// The values shared by all the shaders, bound at set 0. The layout follows
+/// the std140 rules for the uniform block in `shaders/uniforms.glsl`, so
+/// keep the two in sync.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Uniforms {
+    /// Free parameters of the sketch
+    pub params: [f32; 4],
+    /// The amplitude, the frequency, the speed and the octaves of the noise
+    /// displacement in the vertex shader. The amplitude is zero when the
+    /// vertices are displaced on the CPU.
+    pub displacement: [f32; 4],
+    /// The size of the frame in pixels
+    pub resolution: [f32; 2],
+    /// The mouse position in the same coordinates as the vertices, i.e.
+    /// from -1.0 to 1.0 with the y axis pointing up
+    pub mouse: [f32; 2],
+    /// In seconds
+    pub time: f32,
+    pub frame: u32,
+    /// Which noise function displaces the vertices, see `shaders/noise.glsl`
+    pub noise_kind: u32,
+}
+
+unsafe impl bytemuck::Pod for Uniforms {}
+unsafe impl bytemuck::Zeroable for Uniforms {}
+
+const SIZE: wgpu::BufferAddress = std::mem::size_of::<Uniforms>() as wgpu::BufferAddress;
+
+/// A uniform buffer for `Uniforms`, which is updated every frame.
+pub struct UniformBuffer {
+    buffer: wgpu::Buffer,
+    staging: StagingBuffer,
+}
+
+impl UniformBuffer {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer_with_data(
+            bytemuck::bytes_of(&Uniforms::default()),
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        );
+        Self {
+            buffer,
+            staging: StagingBuffer::new(SIZE),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Records the copy of `uniforms` into the buffer.
+    pub fn upload(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uniforms: &Uniforms,
+    ) {
+        self.staging
+            .copy(device, encoder, bytemuck::bytes_of(uniforms), &self.buffer);
+    }
+}
@@ -1,13 +1,39 @@
+use cpu_render::Input;
 use lyon::path::builder::Build;
 use nannou::prelude::*;
+use svg_path::{Artwork, Segment};
+
+// The outline that is striped unless an SVG file is given
+const PATH_DATA: &str = "M0 300 Q200 0 -100 -300";
+
 fn main() {
-    nannou::app(model).update(update).simple_window(view).run();
+    nannou::app(model)
+        .event(event)
+        .update(update)
+        .simple_window(view)
+        .run();
 }
 
-struct Model {}
+struct Model {
+    // The outline of the SVG file, fitted to the window
+    artwork: Option<Vec<Segment>>,
+}
 
 fn model(_app: &App) -> Model {
-    Model {}
+    // Stripe the shapes in an SVG file instead, e.g.:
+    //
+    //     cargo run --release -- artwork.svg
+    let artwork = std::env::args().nth(1).map(|path| {
+        // in the same area as the default outline
+        match Artwork::load(&path).and_then(|a| a.fit([50.0, 0.0], [300.0, 600.0])) {
+            Ok(paths) => paths.concat(),
+            Err(e) => {
+                eprintln!("Failed to load the SVG: {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+    Model { artwork }
 }
 
 fn update(_app: &App, model: &mut Model, _: Update) {}
@@ -28,17 +54,31 @@ impl lyon::geom::traits::Transformation<f32> for MoveX {
     }
 }
 
-fn view(app: &App, _model: &Model, frame: Frame) {
-    let draw = app.draw();
-    let win = app.window_rect();
-    let builder = nannou::geom::path::Builder::new();
+fn to_path(segments: &[Segment]) -> nannou::geom::path::Path {
+    let p = |p: [f32; 2]| pt2(p[0], p[1]);
+    let mut builder = nannou::geom::path::Builder::new();
+    for segment in segments {
+        builder = match *segment {
+            Segment::MoveTo(to) => builder.move_to(p(to)),
+            Segment::LineTo(to) => builder.line_to(p(to)),
+            Segment::QuadraticTo { ctrl, to } => builder.quadratic_bezier_to(p(ctrl), p(to)),
+            Segment::CubicTo { ctrl1, ctrl2, to } => {
+                builder.cubic_bezier_to(p(ctrl1), p(ctrl2), p(to))
+            }
+            Segment::Close => builder.close(),
+        };
+    }
+    builder.build()
+}
+
+fn scene(draw: &cpu_render::Draw, input: &Input, artwork: Option<&[Segment]>) {
+    let win = input.window_rect;
 
     draw.background().color(WHITE);
-    let path_base = builder
-        .move_to(pt2(0.0, 300.0))
-        .quadratic_bezier_to(pt2(200.0, 0.0), pt2(-100.0, -300.0))
-        // .flattened(0.9)
-        .build();
+    let path_base = match artwork {
+        Some(segments) => to_path(segments),
+        None => to_path(&svg_path::parse_path_data(PATH_DATA).unwrap()),
+    };
 
     let mut path = path_base.transformed(&MoveX { x: 0.0 });
     for i in 0..=100 {
@@ -50,6 +90,39 @@ fn view(app: &App, _model: &Model, frame: Frame) {
         path = path.merge(&path_tmp);
     }
     draw.path().fill().color(BLACK).events(path.iter());
+}
 
+// Press V to save the current frame as an SVG file
+fn event(app: &App, model: &mut Model, event: Event) {
+    if let Event::WindowEvent {
+        simple: Some(KeyPressed(Key::V)),
+        ..
+    } = event
+    {
+        let file_name = format!("stripes-{}.svg", app.elapsed_frames());
+        cpu_render::save_svg(app, &file_name, |draw, input| {
+            scene(draw, input, model.artwork.as_deref())
+        });
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let scene_draw = cpu_render::Draw::new();
+    scene(&scene_draw, &Input::from_app(app), model.artwork.as_deref());
+
+    let draw = app.draw();
+    scene_draw.to_nannou(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden() {
+        cpu_render::assert_scene("stripes", &Input::new(1024, 768), |draw, input| {
+            scene(draw, input, None)
+        });
+    }
+}
@@ -0,0 +1,177 @@
+use usvg::prelude::*;
+use usvg::{NodeKind, PathSegment};
+
+use std::path::Path;
+
+use crate::{Outline, Point, Segment};
+
+/// The outlines of the shapes in an SVG file. usvg converts rects, circles,
+/// ellipses, lines, polylines and polygons into paths, and the transforms
+/// of the shapes and their groups are applied. Styles, text and images are
+/// ignored, and so are the shapes that are only referenced, e.g. in
+/// `<defs>` or `<clipPath>`.
+pub struct Artwork {
+    /// The `viewBox` (x, y, width and height) in the coordinates of the
+    /// paths, which are y-down
+    pub view_box: [f32; 4],
+    /// One per shape, in the order they are drawn
+    pub paths: Vec<Vec<Segment>>,
+}
+
+impl Artwork {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        Self::from_data(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parses an SVG document, which may be gzipped.
+    pub fn from_data(data: &[u8]) -> Result<Self, String> {
+        let tree =
+            usvg::Tree::from_data(data, &usvg::Options::default()).map_err(|e| e.to_string())?;
+
+        let rect = tree.svg_node().view_box.rect;
+        let view_box = [
+            rect.x() as f32,
+            rect.y() as f32,
+            rect.width() as f32,
+            rect.height() as f32,
+        ];
+
+        let mut paths = vec![];
+        for node in tree.root().descendants() {
+            if let NodeKind::Path(ref path) = *node.borrow() {
+                let rendered = node.ancestors().skip(1).all(|ancestor| {
+                    matches!(*ancestor.borrow(), NodeKind::Group(_) | NodeKind::Svg(_))
+                });
+                if !rendered {
+                    continue;
+                }
+
+                let mut transform = node.abs_transform();
+                transform.append(&path.transform);
+                paths.push(convert(&path.data, &transform));
+            }
+        }
+
+        Ok(Self { view_box, paths })
+    }
+
+    /// The paths scaled and moved so that the view box fits in the rectangle
+    /// of `size` centred at `center`, keeping the aspect ratio. The y axis is
+    /// flipped, as it points up both in nannou and in clip space. Fails if
+    /// the view box is empty.
+    pub fn fit(&self, center: Point, size: [f32; 2]) -> Result<Vec<Vec<Segment>>, String> {
+        let [x, y, w, h] = self.view_box;
+        if !(w > 0.0 && h > 0.0) {
+            return Err(format!("cannot fit an empty view box: {} x {}", w, h));
+        }
+        let scale = (size[0] / w).min(size[1] / h);
+        let (cx, cy) = (x + w / 2.0, y + h / 2.0);
+        let f = |p: Point| {
+            [
+                center[0] + (p[0] - cx) * scale,
+                center[1] - (p[1] - cy) * scale,
+            ]
+        };
+
+        Ok(self
+            .paths
+            .iter()
+            .map(|path| path.iter().map(|segment| segment.map(f)).collect())
+            .collect())
+    }
+}
+
+fn convert(data: &usvg::PathData, transform: &usvg::Transform) -> Vec<Segment> {
+    let pt = |x: f64, y: f64| {
+        let (x, y) = transform.apply(x, y);
+        [x as f32, y as f32]
+    };
+
+    let mut outline = Outline::default();
+    for segment in data.iter() {
+        match *segment {
+            PathSegment::MoveTo { x, y } => outline.move_to(pt(x, y)),
+            PathSegment::LineTo { x, y } => outline.push(Segment::LineTo(pt(x, y))),
+            PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => outline.push(Segment::CubicTo {
+                ctrl1: pt(x1, y1),
+                ctrl2: pt(x2, y2),
+                to: pt(x, y),
+            }),
+            PathSegment::ClosePath => outline.close(),
+        }
+    }
+    outline.segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 50">
+        <rect x="1" y="2" width="10" height="20" transform="translate(10 0)"/>
+        <g transform="scale(2)">
+            <circle cx="30" cy="12.5" r="5"/>
+        </g>
+        <defs>
+            <rect id="unused" width="10" height="10"/>
+        </defs>
+    </svg>"#;
+
+    fn points(path: &[Segment]) -> Vec<Point> {
+        path.iter()
+            .filter_map(|segment| match *segment {
+                Segment::MoveTo(to) | Segment::LineTo(to) => Some(to),
+                Segment::QuadraticTo { to, .. } | Segment::CubicTo { to, .. } => Some(to),
+                Segment::Close => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transformed_shapes() {
+        let artwork = Artwork::from_data(SVG).unwrap();
+        assert_eq!(artwork.view_box, [0.0, 0.0, 100.0, 50.0]);
+        // the rect in <defs> isn't drawn
+        assert_eq!(artwork.paths.len(), 2);
+
+        let rect = &artwork.paths[0];
+        assert_eq!(
+            points(rect),
+            vec![[11.0, 2.0], [21.0, 2.0], [21.0, 22.0], [11.0, 22.0]]
+        );
+        assert_eq!(rect.last(), Some(&Segment::Close));
+
+        // the circle is scaled by its group
+        for p in points(&artwork.paths[1]) {
+            let r = ((p[0] - 60.0).powi(2) + (p[1] - 25.0).powi(2)).sqrt();
+            assert!((r - 10.0).abs() < 1e-3, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn fit_centres_and_flips() {
+        let artwork = Artwork::from_data(SVG).unwrap();
+        // the view box is scaled by 2 to fit the width
+        let paths = artwork.fit([0.0, 0.0], [200.0, 200.0]).unwrap();
+        assert_eq!(paths[0][0], Segment::MoveTo([-78.0, 46.0]));
+    }
+
+    #[test]
+    fn fit_empty_view_box() {
+        let artwork = Artwork {
+            view_box: [0.0, 0.0, 0.0, 50.0],
+            paths: vec![],
+        };
+        assert!(artwork.fit([0.0, 0.0], [100.0, 100.0]).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use svgtypes::{PathParser, PathSegment};
+
+use crate::{Outline, Point, Segment};
+
+/// Parses SVG path data, e.g. `"M0 300 Q200 0 -100 -300"`. The relative
+/// and the shorthand commands are converted into absolute ones, but the
+/// coordinates are kept as they are, so they are y-down if the data comes
+/// from an SVG file.
+pub fn parse_path_data(d: &str) -> Result<Vec<Segment>, String> {
+    let mut outline = Outline::default();
+    // the current point
+    let (mut x, mut y) = (0.0, 0.0);
+    // the control point of the previous curve, which the smooth curves
+    // reflect
+    let mut prev_cubic_ctrl = None;
+    let mut prev_quad_ctrl = None;
+
+    for token in PathParser::from(d) {
+        let segment = token.map_err(|e| format!("invalid path data: {}", e))?;
+        // relative commands are relative to the current point
+        let (ox, oy) = if segment.is_absolute() {
+            (0.0, 0.0)
+        } else {
+            (x, y)
+        };
+        let mut cubic_ctrl = None;
+        let mut quad_ctrl = None;
+
+        match segment {
+            PathSegment::MoveTo { x: x1, y: y1, .. } => {
+                x = x1 + ox;
+                y = y1 + oy;
+                outline.move_to(pt(x, y));
+            }
+            PathSegment::LineTo { x: x1, y: y1, .. } => {
+                x = x1 + ox;
+                y = y1 + oy;
+                outline.push(Segment::LineTo(pt(x, y)));
+            }
+            PathSegment::HorizontalLineTo { x: x1, .. } => {
+                x = x1 + ox;
+                outline.push(Segment::LineTo(pt(x, y)));
+            }
+            PathSegment::VerticalLineTo { y: y1, .. } => {
+                y = y1 + oy;
+                outline.push(Segment::LineTo(pt(x, y)));
+            }
+            PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x: x3,
+                y: y3,
+                ..
+            } => {
+                let ctrl1 = (x1 + ox, y1 + oy);
+                let ctrl2 = (x2 + ox, y2 + oy);
+                x = x3 + ox;
+                y = y3 + oy;
+                outline.push(cubic(ctrl1, ctrl2, (x, y)));
+                cubic_ctrl = Some(ctrl2);
+            }
+            PathSegment::SmoothCurveTo {
+                x2,
+                y2,
+                x: x3,
+                y: y3,
+                ..
+            } => {
+                let ctrl1 = reflect(prev_cubic_ctrl, (x, y));
+                let ctrl2 = (x2 + ox, y2 + oy);
+                x = x3 + ox;
+                y = y3 + oy;
+                outline.push(cubic(ctrl1, ctrl2, (x, y)));
+                cubic_ctrl = Some(ctrl2);
+            }
+            PathSegment::Quadratic {
+                x1,
+                y1,
+                x: x2,
+                y: y2,
+                ..
+            } => {
+                let ctrl = (x1 + ox, y1 + oy);
+                x = x2 + ox;
+                y = y2 + oy;
+                outline.push(quadratic(ctrl, (x, y)));
+                quad_ctrl = Some(ctrl);
+            }
+            PathSegment::SmoothQuadratic { x: x2, y: y2, .. } => {
+                let ctrl = reflect(prev_quad_ctrl, (x, y));
+                x = x2 + ox;
+                y = y2 + oy;
+                outline.push(quadratic(ctrl, (x, y)));
+                quad_ctrl = Some(ctrl);
+            }
+            PathSegment::EllipticalArc {
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                x: x1,
+                y: y1,
+                ..
+            } => {
+                // usvg knows how to approximate an arc with cubic curves
+                let mut arc = usvg::PathData::new();
+                arc.push_move_to(x, y);
+                x = x1 + ox;
+                y = y1 + oy;
+                arc.push_arc_to(rx, ry, x_axis_rotation, large_arc, sweep, x, y);
+                for segment in arc.iter().skip(1) {
+                    match *segment {
+                        usvg::PathSegment::LineTo { x, y } => {
+                            outline.push(Segment::LineTo(pt(x, y)))
+                        }
+                        usvg::PathSegment::CurveTo {
+                            x1,
+                            y1,
+                            x2,
+                            y2,
+                            x,
+                            y,
+                        } => outline.push(cubic((x1, y1), (x2, y2), (x, y))),
+                        _ => {}
+                    }
+                }
+            }
+            PathSegment::ClosePath { .. } => {
+                outline.close();
+                x = outline.start[0] as f64;
+                y = outline.start[1] as f64;
+            }
+        }
+
+        prev_cubic_ctrl = cubic_ctrl;
+        prev_quad_ctrl = quad_ctrl;
+    }
+
+    Ok(outline.segments)
+}
+
+fn pt(x: f64, y: f64) -> Point {
+    [x as f32, y as f32]
+}
+
+fn quadratic(ctrl: (f64, f64), to: (f64, f64)) -> Segment {
+    Segment::QuadraticTo {
+        ctrl: pt(ctrl.0, ctrl.1),
+        to: pt(to.0, to.1),
+    }
+}
+
+fn cubic(ctrl1: (f64, f64), ctrl2: (f64, f64), to: (f64, f64)) -> Segment {
+    Segment::CubicTo {
+        ctrl1: pt(ctrl1.0, ctrl1.1),
+        ctrl2: pt(ctrl2.0, ctrl2.1),
+        to: pt(to.0, to.1),
+    }
+}
+
+// The first control point of a smooth curve, which is the reflection of the
+// previous one about the current point, or the current point itself if the
+// previous segment isn't a curve of the same kind.
+fn reflect(prev_ctrl: Option<(f64, f64)>, current: (f64, f64)) -> (f64, f64) {
+    match prev_ctrl {
+        Some((cx, cy)) => (2.0 * current.0 - cx, 2.0 * current.1 - cy),
+        None => current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(d: &str) -> Vec<Segment> {
+        parse_path_data(d).unwrap()
+    }
+
+    fn assert_close(a: Point, b: Point) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn relative_commands() {
+        assert_eq!(
+            parse("m10 20 l5 5 q5 0 5 5 c0 5 5 5 5 0"),
+            vec![
+                Segment::MoveTo([10.0, 20.0]),
+                Segment::LineTo([15.0, 25.0]),
+                Segment::QuadraticTo {
+                    ctrl: [20.0, 25.0],
+                    to: [20.0, 30.0],
+                },
+                Segment::CubicTo {
+                    ctrl1: [20.0, 35.0],
+                    ctrl2: [25.0, 35.0],
+                    to: [25.0, 30.0],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn horizontal_and_vertical_lines() {
+        assert_eq!(
+            parse("M1 2 H5 v3 h-2 V0"),
+            vec![
+                Segment::MoveTo([1.0, 2.0]),
+                Segment::LineTo([5.0, 2.0]),
+                Segment::LineTo([5.0, 5.0]),
+                Segment::LineTo([3.0, 5.0]),
+                Segment::LineTo([3.0, 0.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn smooth_cubic_reflects_the_previous_control_point() {
+        assert_eq!(
+            parse("M0 0 C0 10 10 10 10 0 S20 -10 20 0")[2],
+            Segment::CubicTo {
+                ctrl1: [10.0, -10.0],
+                ctrl2: [20.0, -10.0],
+                to: [20.0, 0.0],
+            }
+        );
+        // without a previous cubic curve, the current point is the control
+        // point
+        assert_eq!(
+            parse("M0 0 L5 5 s5 5 10 0")[2],
+            Segment::CubicTo {
+                ctrl1: [5.0, 5.0],
+                ctrl2: [10.0, 10.0],
+                to: [15.0, 5.0],
+            }
+        );
+    }
+
+    #[test]
+    fn smooth_quadratic_reflects_the_previous_control_point() {
+        assert_eq!(
+            parse("M0 0 Q5 10 10 0 T20 0 t10 0"),
+            vec![
+                Segment::MoveTo([0.0, 0.0]),
+                Segment::QuadraticTo {
+                    ctrl: [5.0, 10.0],
+                    to: [10.0, 0.0],
+                },
+                Segment::QuadraticTo {
+                    ctrl: [15.0, -10.0],
+                    to: [20.0, 0.0],
+                },
+                Segment::QuadraticTo {
+                    ctrl: [25.0, 10.0],
+                    to: [30.0, 0.0],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn implicit_move_to_after_close() {
+        assert_eq!(
+            parse("M10 10 h5 v5 z l1 1 z m2 2 h1"),
+            vec![
+                Segment::MoveTo([10.0, 10.0]),
+                Segment::LineTo([15.0, 10.0]),
+                Segment::LineTo([15.0, 15.0]),
+                Segment::Close,
+                // the next subpath starts where the closed one started
+                Segment::MoveTo([10.0, 10.0]),
+                Segment::LineTo([11.0, 11.0]),
+                Segment::Close,
+                Segment::MoveTo([12.0, 12.0]),
+                Segment::LineTo([13.0, 12.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn arc_is_converted_into_cubic_curves() {
+        // a half circle of radius 5 from (0, 0) to (10, 0)
+        let segments = parse("M0 0 a5 5 0 0 1 10 0");
+        assert_eq!(segments[0], Segment::MoveTo([0.0, 0.0]));
+        assert!(segments.len() > 2);
+
+        let mut last = [0.0, 0.0];
+        for segment in &segments[1..] {
+            match *segment {
+                Segment::CubicTo { to, .. } => {
+                    // the ends of the curves are on the circle
+                    let r = ((to[0] - 5.0).powi(2) + to[1].powi(2)).sqrt();
+                    assert!((r - 5.0).abs() < 1e-3);
+                    last = to;
+                }
+                _ => panic!("unexpected {:?}", segment),
+            }
+        }
+        assert_close(last, [10.0, 0.0]);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(parse_path_data("M0 0 L x").is_err());
+    }
+}
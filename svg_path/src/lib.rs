@@ -0,0 +1,98 @@
+//! Loads outlines from SVG, either from path data (the `d` attribute of a
+//! `<path>`) with [`parse_path_data`] or from a whole file with
+//! [`Artwork`], so that artwork made in a vector graphics editor can be
+//! tessellated or striped by the sketches.
+//!
+//! The sketches depend on different versions of lyon, so this crate doesn't
+//! depend on lyon and returns its own [`Segment`]s instead, which map one to
+//! one to the methods of lyon's path builder:
+//!
+//! ```ignore
+//! let mut builder = lyon::path::Path::builder();
+//! for segment in svg_path::parse_path_data("M0 0 Q100 100 200 0 Z")? {
+//!     match segment {
+//!         Segment::MoveTo(p) => builder.move_to(point(p[0], p[1])),
+//!         Segment::LineTo(p) => builder.line_to(point(p[0], p[1])),
+//!         // ...
+//!     }
+//! }
+//! let path = builder.build();
+//! ```
+
+mod artwork;
+mod data;
+
+pub use artwork::Artwork;
+pub use data::parse_path_data;
+
+pub type Point = [f32; 2];
+
+/// A segment of an outline in absolute coordinates. Every subpath starts
+/// with a `MoveTo`, and arcs are converted into cubic curves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadraticTo {
+        ctrl: Point,
+        to: Point,
+    },
+    CubicTo {
+        ctrl1: Point,
+        ctrl2: Point,
+        to: Point,
+    },
+    /// Closes the current subpath
+    Close,
+}
+
+impl Segment {
+    /// Applies `f` to all the points of the segment.
+    pub fn map<F: Fn(Point) -> Point>(self, f: F) -> Self {
+        match self {
+            Segment::MoveTo(p) => Segment::MoveTo(f(p)),
+            Segment::LineTo(p) => Segment::LineTo(f(p)),
+            Segment::QuadraticTo { ctrl, to } => Segment::QuadraticTo {
+                ctrl: f(ctrl),
+                to: f(to),
+            },
+            Segment::CubicTo { ctrl1, ctrl2, to } => Segment::CubicTo {
+                ctrl1: f(ctrl1),
+                ctrl2: f(ctrl2),
+                to: f(to),
+            },
+            Segment::Close => Segment::Close,
+        }
+    }
+}
+
+// Collects the segments and inserts the `MoveTo` that SVG leaves implicit
+// after a `Z`.
+#[derive(Default)]
+struct Outline {
+    segments: Vec<Segment>,
+    start: Point,
+    closed: bool,
+}
+
+impl Outline {
+    fn move_to(&mut self, p: Point) {
+        self.segments.push(Segment::MoveTo(p));
+        self.start = p;
+        self.closed = false;
+    }
+
+    fn push(&mut self, segment: Segment) {
+        if self.closed {
+            self.move_to(self.start);
+        }
+        self.segments.push(segment);
+    }
+
+    fn close(&mut self) {
+        if !self.closed {
+            self.segments.push(Segment::Close);
+            self.closed = true;
+        }
+    }
+}